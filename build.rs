@@ -0,0 +1,9 @@
+fn main() {
+    // Plugins call the C API, so the binary exports it for them to link against.
+    if std::env::var("CARGO_CFG_TARGET_FAMILY").as_deref() == Ok("unix") {
+        println!("cargo:rustc-link-arg-bins=-rdynamic");
+    }
+    // Lets the Node.js addon link on macOS, where Node provides the N-API symbols.
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
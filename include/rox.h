@@ -0,0 +1,67 @@
+/* The C API for embedding rox. Build the library with `cargo build --release` and link
+ * against target/release/librox.so (or .dylib, or rox.dll). */
+#ifndef ROX_H
+#define ROX_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct RoxVm RoxVm;
+typedef struct RoxValue RoxValue;
+
+typedef enum RoxValueType {
+  ROX_NIL = 0,
+  ROX_BOOL = 1,
+  ROX_NUMBER = 2,
+  ROX_STRING = 3,
+  ROX_OTHER = 4,
+} RoxValueType;
+
+/* A host function callable from Lox. It receives `argc` borrowed arguments and returns a
+ * new value, which rox takes ownership of, or NULL to raise a runtime error. */
+typedef RoxValue *(*RoxFn)(void *user_data, const RoxValue *const *args, size_t argc);
+
+RoxVm *rox_new(void);
+void rox_free(RoxVm *vm);
+
+/* Returns 0 on success, 65 on compile errors, 70 on a runtime error, or the code passed to
+ * exit(). */
+int rox_eval(RoxVm *vm, const char *source);
+/* The error from the last rox_eval, or "". Valid until the next rox_eval or rox_free. */
+const char *rox_last_error(const RoxVm *vm);
+
+/* Defines a global native. `arity` is the exact number of arguments, or -1 for any number. */
+void rox_register_fn(RoxVm *vm, const char *name, int arity, RoxFn function, void *user_data);
+/* Returns a copy of a global, or NULL if there is none. */
+RoxValue *rox_get_global(const RoxVm *vm, const char *name);
+
+/* A plugin is a shared library that defines this entry point, loaded with `rox --plugin`
+ * or loadPlugin(). It registers its natives with rox_register_fn and returns 0, or
+ * anything else if it could not. Plugins link against the rox binary's copy of this API,
+ * so build them without linking librox. */
+int rox_plugin_init(RoxVm *vm);
+
+RoxValue *rox_value_nil(void);
+RoxValue *rox_value_bool(bool b);
+RoxValue *rox_value_number(double n);
+RoxValue *rox_value_string(const char *s);
+void rox_value_free(RoxValue *value);
+
+RoxValueType rox_value_type(const RoxValue *value);
+/* False if the value is not a bool. */
+bool rox_value_as_bool(const RoxValue *value);
+/* NaN if the value is not a number. */
+double rox_value_as_number(const RoxValue *value);
+/* The value as `print` shows it; release it with rox_string_free. */
+char *rox_value_to_string(const RoxValue *value);
+void rox_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -1,26 +1,50 @@
-use crate::tokens::{Literal, Token};
+use crate::span::Span;
+use crate::tokens::{Literal, Token, TokenType};
+use std::fmt;
 
 #[derive(PartialEq, Clone, Debug)]
-pub enum Stmt {
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub const fn new(kind: StmtKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum StmtKind {
     Block(Vec<Stmt>),
+    Break(Token),
+    Continue(Token),
+    DoWhile(Box<Stmt>, Expr),
     Expression(Expr),
     If(Expr, Box<Stmt>, Box<Option<Stmt>>),
     Print(Expr),
     Var(Token, Option<Expr>),
-    While(Expr, Box<Stmt>)
+    /// A loop with an optional increment that runs after each pass through the body, even
+    /// one cut short by `continue`. `for` loops desugar to this.
+    While(Expr, Box<Stmt>, Option<Expr>),
 }
 
 pub trait StmtVisitor<T> {
     fn execute(&mut self, stmt: Stmt) -> T {
-        match stmt {
-            Stmt::If(condition, then_branch, else_branch) => {
+        match stmt.kind {
+            StmtKind::If(condition, then_branch, else_branch) => {
                 self.visit_if_stmt(condition, then_branch, else_branch)
             }
-            Stmt::Block(stmts) => self.visit_block_stmt(stmts),
-            Stmt::Expression(stmt) => self.visit_expression_stmt(stmt),
-            Stmt::Print(stmt) => self.visit_print_stmt(stmt),
-            Stmt::Var(name, initializer) => self.visit_var_stmt(name, initializer),
-            Stmt::While(condition, body) => self.visit_while_stmt(condition, body),
+            StmtKind::Block(stmts) => self.visit_block_stmt(stmts),
+            StmtKind::Break(keyword) => self.visit_break_stmt(keyword),
+            StmtKind::Continue(keyword) => self.visit_continue_stmt(keyword),
+            StmtKind::DoWhile(body, condition) => self.visit_do_while_stmt(body, condition),
+            StmtKind::Expression(stmt) => self.visit_expression_stmt(stmt),
+            StmtKind::Print(stmt) => self.visit_print_stmt(stmt),
+            StmtKind::Var(name, initializer) => self.visit_var_stmt(name, initializer),
+            StmtKind::While(condition, body, increment) => {
+                self.visit_while_stmt(condition, body, increment)
+            }
         }
     }
     fn visit_if_stmt(
@@ -30,40 +54,169 @@ pub trait StmtVisitor<T> {
         else_branch: Box<Option<Stmt>>,
     ) -> T;
     fn visit_block_stmt(&mut self, stmts: Vec<Stmt>) -> T;
+    fn visit_break_stmt(&mut self, keyword: Token) -> T;
+    fn visit_continue_stmt(&mut self, keyword: Token) -> T;
+    fn visit_do_while_stmt(&mut self, body: Box<Stmt>, condition: Expr) -> T;
     fn visit_expression_stmt(&mut self, stmt: Expr) -> T;
     fn visit_print_stmt(&mut self, stmt: Expr) -> T;
     fn visit_var_stmt(&mut self, name: Token, initializer: Option<Expr>) -> T;
-    fn visit_while_stmt(&mut self, condition: Expr, body: Box<Stmt>) -> T;
+    fn visit_while_stmt(&mut self, condition: Expr, body: Box<Stmt>, increment: Option<Expr>) -> T;
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub const fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// How many levels deep the tree under this expression goes, counting itself.
+    pub fn height(&self) -> usize {
+        let children: Vec<&Self> = match &self.kind {
+            ExprKind::Assign(_, value)
+            | ExprKind::Get(value, _)
+            | ExprKind::Grouping(value)
+            | ExprKind::SetGlobal(_, value)
+            | ExprKind::Unary(_, value) => vec![value],
+            ExprKind::Binary(left, _, right)
+            | ExprKind::Index(left, _, right)
+            | ExprKind::Logical(left, _, right) => vec![left, right],
+            ExprKind::Call(callee, _, arguments) => {
+                std::iter::once(&**callee).chain(arguments).collect()
+            }
+            ExprKind::Map(_, entries) => entries
+                .iter()
+                .flat_map(|(key, value)| vec![key, value])
+                .collect(),
+            ExprKind::SetIndex(object, _, index, value) => vec![object, index, value],
+            ExprKind::Global(_) | ExprKind::Literal(_) | ExprKind::Variable(_) => Vec::new(),
+        };
+        1 + children.into_iter().map(Self::height).max().unwrap_or(0)
+    }
+
+    /// The operands of a chain of comma operators, which group to the left, or just this
+    /// expression if it is not one.
+    pub fn comma_operands(&self) -> Vec<&Self> {
+        match &self.kind {
+            ExprKind::Binary(left, operator, right) if operator.type_ == TokenType::Comma => {
+                let mut operands = left.comma_operands();
+                operands.push(right);
+                operands
+            }
+            _ => vec![self],
+        }
+    }
+}
+
+/// Prints the expression as a fully parenthesized prefix tree, such as `(+ 1 (* 2 x))`.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ExprKind::Assign(name, value) => write!(f, "(= {} {value})", name.lexeme),
+            ExprKind::Binary(left, operator, right) | ExprKind::Logical(left, operator, right) => {
+                write!(f, "({} {left} {right})", operator.lexeme)
+            }
+            ExprKind::Call(callee, _, arguments) => {
+                write!(f, "(call {callee}")?;
+                for argument in arguments {
+                    write!(f, " {argument}")?;
+                }
+                write!(f, ")")
+            }
+            ExprKind::Get(object, name) => write!(f, "(. {object} {})", name.lexeme),
+            ExprKind::Global(name) => write!(f, "(global {})", name.lexeme),
+            ExprKind::Grouping(expr) => write!(f, "(group {expr})"),
+            ExprKind::Index(object, _, index) => write!(f, "([] {object} {index})"),
+            ExprKind::Literal(Literal::String_(s)) => write!(f, "{s:?}"),
+            ExprKind::Literal(Literal::Number(n)) => write!(f, "{n}"),
+            ExprKind::Literal(Literal::BigInt(n)) => write!(f, "{n}n"),
+            ExprKind::Literal(Literal::Bool(b)) => write!(f, "{b}"),
+            ExprKind::Literal(Literal::Nil) => write!(f, "nil"),
+            ExprKind::Map(_, entries) => {
+                write!(f, "(map")?;
+                for (key, value) in entries {
+                    write!(f, " {key} {value}")?;
+                }
+                write!(f, ")")
+            }
+            ExprKind::SetGlobal(name, value) => write!(f, "(= (global {}) {value})", name.lexeme),
+            ExprKind::SetIndex(object, _, index, value) => {
+                write!(f, "([]= {object} {index} {value})")
+            }
+            ExprKind::Unary(operator, right) => write!(f, "({} {right})", operator.lexeme),
+            ExprKind::Variable(name) => write!(f, "{}", name.lexeme),
+        }
+    }
 }
 
 #[derive(PartialEq, Clone, Debug)]
-pub enum Expr {
+pub enum ExprKind {
     Assign(Token, Box<Expr>),
     Binary(Box<Expr>, Token, Box<Expr>),
+    Call(Box<Expr>, Token, Vec<Expr>),
+    Get(Box<Expr>, Token),
+    /// `global.name`, which reads a global even where a local shadows it.
+    Global(Token),
     Grouping(Box<Expr>),
+    Index(Box<Expr>, Token, Box<Expr>),
     Literal(Literal),
     Logical(Box<Expr>, Token, Box<Expr>),
+    Map(Token, Vec<(Expr, Expr)>),
+    SetGlobal(Token, Box<Expr>),
+    SetIndex(Box<Expr>, Token, Box<Expr>, Box<Expr>),
     Unary(Token, Box<Expr>),
     Variable(Token),
 }
 
 pub trait ExprVisitor<T> {
     fn evaluate(&mut self, expr: Expr) -> T {
-        match expr {
-            Expr::Assign(name, value) => self.visit_assign_expr(name, value),
-            Expr::Binary(b, o, b2) => self.visit_binary_expr(b, o, b2),
-            Expr::Grouping(g) => self.visit_grouping_expr(g),
-            Expr::Literal(l) => self.visit_literal_expr(l),
-            Expr::Unary(operator, right) => self.visit_unary_expr(operator, right),
-            Expr::Variable(v) => self.visit_variable_expr(v),
-            Expr::Logical(left, operator, right) => self.visit_logical_expr(left, operator, right),
+        match expr.kind {
+            ExprKind::Assign(name, value) => self.visit_assign_expr(name, value),
+            ExprKind::Binary(b, o, b2) => self.visit_binary_expr(b, o, b2),
+            ExprKind::Call(callee, paren, arguments) => {
+                self.visit_call_expr(callee, paren, arguments)
+            }
+            ExprKind::Get(object, name) => self.visit_get_expr(object, name),
+            ExprKind::Global(name) => self.visit_global_expr(name),
+            ExprKind::Grouping(g) => self.visit_grouping_expr(g),
+            ExprKind::Index(object, bracket, index) => {
+                self.visit_index_expr(object, bracket, index)
+            }
+            ExprKind::Literal(l) => self.visit_literal_expr(l),
+            ExprKind::Map(brace, entries) => self.visit_map_expr(brace, entries),
+            ExprKind::SetGlobal(name, value) => self.visit_set_global_expr(name, value),
+            ExprKind::SetIndex(object, bracket, index, value) => {
+                self.visit_set_index_expr(object, bracket, index, value)
+            }
+            ExprKind::Unary(operator, right) => self.visit_unary_expr(operator, right),
+            ExprKind::Variable(v) => self.visit_variable_expr(v),
+            ExprKind::Logical(left, operator, right) => {
+                self.visit_logical_expr(left, operator, right)
+            }
         }
     }
     fn visit_assign_expr(&mut self, name: Token, value: Box<Expr>) -> T;
     fn visit_binary_expr(&mut self, left: Box<Expr>, operator: Token, right: Box<Expr>) -> T;
+    fn visit_call_expr(&mut self, callee: Box<Expr>, paren: Token, arguments: Vec<Expr>) -> T;
+    fn visit_get_expr(&mut self, object: Box<Expr>, name: Token) -> T;
+    fn visit_global_expr(&mut self, name: Token) -> T;
     fn visit_grouping_expr(&mut self, expr: Box<Expr>) -> T;
+    fn visit_index_expr(&mut self, object: Box<Expr>, bracket: Token, index: Box<Expr>) -> T;
     fn visit_literal_expr(&mut self, literal: Literal) -> T;
     fn visit_logical_expr(&mut self, left: Box<Expr>, operator: Token, right: Box<Expr>) -> T;
+    fn visit_map_expr(&mut self, brace: Token, entries: Vec<(Expr, Expr)>) -> T;
+    fn visit_set_global_expr(&mut self, name: Token, value: Box<Expr>) -> T;
+    fn visit_set_index_expr(
+        &mut self,
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    ) -> T;
     fn visit_unary_expr(&mut self, operator: Token, right: Box<Expr>) -> T;
     fn visit_variable_expr(&self, expr: Token) -> T;
 }
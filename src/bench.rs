@@ -0,0 +1,99 @@
+use crate::cli::Options;
+use anyhow::Result;
+use rox::interpreter::{Exit, Interpreter, Output};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::convert::TryFrom;
+use std::io;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Counts heap allocations so `rox bench` can report them alongside timings.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Parses the script once, then runs it `options.warmup` times untimed and
+/// `options.iterations` times timed, each in a fresh interpreter with output discarded.
+pub fn run<P: AsRef<Path>>(filename: P, options: &Options) -> Result<()> {
+    let filename = filename.as_ref();
+    let contents = crate::read_source(filename, options)?;
+    let statements = match rox::parse(&contents) {
+        Ok(statements) => statements,
+        Err(diagnostics) => {
+            let _ = crate::report_diagnostics(&diagnostics);
+            process::exit(65);
+        }
+    };
+
+    let mut times = Vec::with_capacity(options.iterations);
+    let mut allocations = 0;
+    for run in 0..options.warmup + options.iterations {
+        let mut interpreter = Interpreter::with_output(Output::new(io::sink()));
+        for &capability in &options.capabilities {
+            interpreter.allow(capability);
+        }
+        if let Some(seed) = options.seed {
+            interpreter.seed_random(seed);
+        }
+        if let Some(level) = options.log_level {
+            interpreter.set_log_level(level);
+        }
+        let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let result = interpreter.interpret(&statements);
+        let elapsed = start.elapsed();
+        let allocated = ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+        if let Err(error) = result {
+            // exit() just ends the run early; anything else means the benchmark is broken.
+            if !error.is::<Exit>() {
+                crate::report_runtime_error(&error);
+                process::exit(70);
+            }
+        }
+        if run >= options.warmup {
+            times.push(elapsed);
+            allocations += allocated;
+        }
+    }
+
+    times.sort();
+    let runs = u32::try_from(times.len())?;
+    let mean = times.iter().sum::<Duration>() / runs;
+    let median = if times.len() % 2 == 0 {
+        (times[times.len() / 2 - 1] + times[times.len() / 2]) / 2
+    } else {
+        times[times.len() / 2]
+    };
+    println!(
+        "{}: {} runs after {} warmup runs",
+        filename.display(),
+        times.len(),
+        options.warmup
+    );
+    println!("  min          {:.3?}", times[0]);
+    println!("  median       {median:.3?}");
+    println!("  mean         {mean:.3?}");
+    println!("  allocations  {} per run", allocations / times.len());
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use std::fmt;
+
+/// Access to the outside world that a host must grant before scripts can use the natives
+/// needing it. Interpreters start with none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Running other programs, as `Process.run` does.
+    Process,
+    /// Opening and accepting network connections, as the `Net` natives do.
+    Net,
+    /// Loading native code from shared libraries, as `loadPlugin` does.
+    Plugins,
+    /// Looking at the file system beyond the script itself, as `Path.canonicalize` and the
+    /// directory natives in `Os` do.
+    Files,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Process => write!(f, "process"),
+            Self::Net => write!(f, "net"),
+            Self::Plugins => write!(f, "plugins"),
+            Self::Files => write!(f, "files"),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use crate::ast::{Expr, ExprKind, Stmt, StmtKind};
+use crate::span::Span;
+use crate::tokens::Literal;
+use std::fmt::Write;
+
+/// A control-flow graph of a program, built from its AST.
+///
+/// Block 0 is the entry and the last block is the exit. Straight-line statements share a
+/// block; `if` and loop conditions end one, and `break` and `continue` jump out of one.
+/// Branches on a literal `true` or `false` only get the edge that can be taken, so code
+/// they skip has no way in.
+#[derive(Debug)]
+pub struct Cfg<'a> {
+    pub blocks: Vec<Block<'a>>,
+}
+
+#[derive(Debug, Default)]
+pub struct Block<'a> {
+    pub items: Vec<Item<'a>>,
+    /// The condition the block ends by branching on, if any.
+    pub branch: Option<&'a Expr>,
+    pub successors: Vec<Edge>,
+}
+
+/// Something a block runs: a simple statement, or a `for` loop's increment.
+#[derive(Debug, Clone, Copy)]
+pub enum Item<'a> {
+    Statement(&'a Stmt),
+    Expression(&'a Expr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub target: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Jump,
+    True,
+    False,
+}
+
+/// Where `continue` and `break` go inside the innermost loop.
+struct LoopTargets {
+    continue_to: usize,
+    break_to: usize,
+}
+
+struct Builder<'a> {
+    blocks: Vec<Block<'a>>,
+    /// The block statements are being added to, or `None` after a jump, where nothing
+    /// can flow.
+    current: Option<usize>,
+    loops: Vec<LoopTargets>,
+}
+
+impl<'a> Cfg<'a> {
+    pub fn build(statements: &'a [Stmt]) -> Self {
+        let mut builder = Builder {
+            blocks: Vec::new(),
+            current: None,
+            loops: Vec::new(),
+        };
+        let entry = builder.new_block();
+        builder.current = Some(entry);
+        builder.statements(statements);
+        let exit = builder.new_block();
+        builder.jump_to(exit);
+        Self {
+            blocks: builder.blocks,
+        }
+    }
+
+    pub const fn exit(&self) -> usize {
+        self.blocks.len() - 1
+    }
+
+    /// Returns whether each block can be reached from the entry.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut pending = vec![0];
+        while let Some(block) = pending.pop() {
+            if !reachable[block] {
+                reachable[block] = true;
+                pending.extend(self.blocks[block].successors.iter().map(|edge| edge.target));
+            }
+        }
+        reachable
+    }
+
+    /// Returns the spans of code that can never run, one per stretch of unreachable
+    /// statements in source order.
+    pub fn unreachable_code(&self) -> Vec<Span> {
+        let reachable = self.reachable();
+        let mut spans: Vec<(Span, bool)> = Vec::new();
+        for (block, reachable) in self.blocks.iter().zip(reachable) {
+            let items = block.items.iter().map(|item| match item {
+                Item::Statement(stmt) => stmt.span,
+                Item::Expression(expr) => expr.span,
+            });
+            let branch = block.branch.map(|condition| condition.span);
+            spans.extend(items.chain(branch).map(|span| (span, reachable)));
+        }
+        spans.sort_by_key(|(span, _)| span.start);
+
+        let mut stretches: Vec<Span> = Vec::new();
+        let mut in_stretch = false;
+        for (span, reachable) in spans {
+            match stretches.last_mut() {
+                _ if reachable => in_stretch = false,
+                Some(stretch) if in_stretch => *stretch = stretch.to(span),
+                _ => {
+                    stretches.push(span);
+                    in_stretch = true;
+                }
+            }
+        }
+        stretches
+    }
+
+    /// Renders the graph in Graphviz DOT, quoting each block's code from `source`.
+    pub fn to_dot(&self, source: &str) -> String {
+        let mut dot = String::from("digraph cfg {\n  node [shape=box, fontname=\"monospace\"];\n");
+        for (id, block) in self.blocks.iter().enumerate() {
+            let mut label = match id {
+                0 => "entry\\l".to_owned(),
+                _ if id == self.exit() => "exit\\l".to_owned(),
+                _ => String::new(),
+            };
+            for item in &block.items {
+                let span = match item {
+                    Item::Statement(stmt) => stmt.span,
+                    Item::Expression(expr) => expr.span,
+                };
+                label += &escape(source.get(span.start..span.end).unwrap_or_default());
+                label += "\\l";
+            }
+            if let Some(branch) = block.branch {
+                let condition = source.get(branch.span.start..branch.span.end);
+                let condition = escape(condition.unwrap_or_default());
+                let _ = write!(label, "branch on {condition}\\l");
+            }
+            let _ = writeln!(dot, "  b{id} [label=\"{label}\"];");
+            for edge in &block.successors {
+                let attributes = match edge.kind {
+                    EdgeKind::Jump => "",
+                    EdgeKind::True => " [label=\"true\"]",
+                    EdgeKind::False => " [label=\"false\"]",
+                };
+                let _ = writeln!(dot, "  b{id} -> b{}{attributes};", edge.target);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl<'a> Builder<'a> {
+    fn new_block(&mut self) -> usize {
+        self.blocks.push(Block::default());
+        self.blocks.len() - 1
+    }
+
+    /// Returns the current block, starting an unreachable one if control cannot get here.
+    fn current(&mut self) -> usize {
+        if let Some(block) = self.current {
+            block
+        } else {
+            let block = self.new_block();
+            self.current = Some(block);
+            block
+        }
+    }
+
+    fn edge(&mut self, from: usize, target: usize, kind: EdgeKind) {
+        self.blocks[from].successors.push(Edge { target, kind });
+    }
+
+    /// Ends the current block, if control can reach the end of it, with a jump to `target`.
+    fn jump_to(&mut self, target: usize) {
+        if let Some(block) = self.current.take() {
+            self.edge(block, target, EdgeKind::Jump);
+        }
+    }
+
+    /// Ends `from` with a branch on `condition`, leaving out the edge a literal `true` or
+    /// `false` can never take.
+    fn branch(&mut self, from: usize, condition: &'a Expr, if_true: usize, if_false: usize) {
+        self.blocks[from].branch = Some(condition);
+        let constant = constant_condition(condition);
+        if constant != Some(false) {
+            self.edge(from, if_true, EdgeKind::True);
+        }
+        if constant != Some(true) {
+            self.edge(from, if_false, EdgeKind::False);
+        }
+    }
+
+    fn statements(&mut self, statements: &'a [Stmt]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, stmt: &'a Stmt) {
+        match &stmt.kind {
+            StmtKind::Block(statements) => self.statements(statements),
+            StmtKind::Break(_) | StmtKind::Continue(_) => {
+                let block = self.current();
+                self.blocks[block].items.push(Item::Statement(stmt));
+                let target = self.loops.last().map(|targets| match stmt.kind {
+                    StmtKind::Break(_) => targets.break_to,
+                    _ => targets.continue_to,
+                });
+                // Outside a loop this is a compile error; just end the block.
+                match target {
+                    Some(target) => self.jump_to(target),
+                    None => self.current = None,
+                }
+            }
+            StmtKind::DoWhile(body, condition) => {
+                let body_block = self.new_block();
+                let condition_block = self.new_block();
+                let after = self.new_block();
+                self.jump_to(body_block);
+                self.current = Some(body_block);
+                self.loop_body(body, condition_block, after);
+                self.jump_to(condition_block);
+                self.branch(condition_block, condition, body_block, after);
+                self.current = Some(after);
+            }
+            StmtKind::Expression(_) | StmtKind::Print(_) | StmtKind::Var(_, _) => {
+                let block = self.current();
+                self.blocks[block].items.push(Item::Statement(stmt));
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                let block = self.current();
+                let then_block = self.new_block();
+                let else_block = else_branch.as_ref().as_ref().map(|_| self.new_block());
+                let after = self.new_block();
+                self.branch(block, condition, then_block, else_block.unwrap_or(after));
+                self.current = Some(then_block);
+                self.statement(then_branch);
+                self.jump_to(after);
+                if let (Some(else_block), Some(else_branch)) = (else_block, else_branch.as_ref()) {
+                    self.current = Some(else_block);
+                    self.statement(else_branch);
+                    self.jump_to(after);
+                }
+                self.current = Some(after);
+            }
+            StmtKind::While(condition, body, increment) => {
+                let header = self.new_block();
+                let body_block = self.new_block();
+                let increment_block = increment.as_ref().map(|_| self.new_block());
+                let after = self.new_block();
+                self.jump_to(header);
+                self.branch(header, condition, body_block, after);
+                self.current = Some(body_block);
+                self.loop_body(body, increment_block.unwrap_or(header), after);
+                if let (Some(increment_block), Some(increment)) = (increment_block, increment) {
+                    self.jump_to(increment_block);
+                    self.blocks[increment_block]
+                        .items
+                        .push(Item::Expression(increment));
+                    self.current = Some(increment_block);
+                }
+                self.jump_to(header);
+                self.current = Some(after);
+            }
+        }
+    }
+
+    fn loop_body(&mut self, body: &'a Stmt, continue_to: usize, break_to: usize) {
+        self.loops.push(LoopTargets {
+            continue_to,
+            break_to,
+        });
+        self.statement(body);
+        self.loops.pop();
+    }
+}
+
+/// The value of a condition that is a boolean literal, possibly parenthesized.
+fn constant_condition(condition: &Expr) -> Option<bool> {
+    match &condition.kind {
+        ExprKind::Literal(Literal::Bool(b)) => Some(*b),
+        ExprKind::Grouping(inner) => constant_condition(inner),
+        _ => None,
+    }
+}
+
+/// Escapes source text for a double-quoted DOT label.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\l")
+}
@@ -0,0 +1,233 @@
+use anyhow::Result;
+use rox::capability::Capability;
+use rox::log;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub const USAGE: &str = "\
+Usage: rox [options] [script]
+       rox [options] run <script>
+       rox [options] repl
+       rox [options] bench <script>
+       rox [options] graph <script>
+       rox [options] cfg <script>
+       rox [options] emit-js <script>
+       rox [options] cover <script>
+
+Runs a Lox script, or starts an interactive prompt when no script is given.
+
+Commands:
+    run <script>    Run a script
+    repl            Start the interactive prompt, where ':type <expr>' prints the type
+                    of an expression and ':ast <expr>' prints its syntax tree
+    bench <script>  Run a script repeatedly and report how long it took
+    graph <script>  Print the script's syntax tree as a Graphviz DOT graph
+    cfg <script>    Print the script's control-flow graph as a Graphviz DOT graph
+    emit-js <script>
+                    Print the script translated to JavaScript
+    cover <script>  Run a script, then print its source annotated with how many times
+                    each line ran, to standard error
+
+Options:
+    --dump-tokens   Print the scanned tokens, including comments, before running
+    --dump-ast      Print the parsed syntax tree before running
+    --time          Report how long each phase took after running
+    --allow-process Let the script run other programs with Process.run
+    --allow-net     Let the script open network connections with Net and Http, and
+                    read the machine's name with Os.hostname
+    --allow-plugins Let the script load native plugins with loadPlugin
+    --allow-files   Let the script look at the file system with Path.canonicalize,
+                    Os.cwd, Os.chdir and Os.listDir
+    --plugin PATH   Load a native plugin before running; may be repeated
+    --iterations N  Number of timed runs for bench (default 10)
+    --warmup N      Number of untimed runs bench does first (default 2)
+    --lcov PATH     Also write cover's results to an lcov tracefile
+    --lossy-utf8    Replace invalid UTF-8 in the script instead of refusing to run it
+    --max-source-size BYTES
+                    Refuse scripts larger than this (default 16777216)
+    --log-level LEVEL
+                    Write Log messages at LEVEL and above: debug, info (default), warn or
+                    error
+    --seed N        Seed random() and randomInt() so every run gets the same numbers
+    --record PATH   Save what random numbers, programs, HTTP requests and standard input
+                    the script got to a trace file
+    --replay PATH   Give the script the results saved by --record instead
+    --no-color      Do not highlight input at the interactive prompt
+    -h, --help      Print this help and exit
+    -V, --version   Print the version and exit";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Run(PathBuf),
+    Repl,
+    Bench(PathBuf),
+    Graph(PathBuf),
+    Cfg(PathBuf),
+    EmitJs(PathBuf),
+    Cover(PathBuf),
+    Help,
+    Version,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Options {
+    pub command: Command,
+    pub dump_tokens: bool,
+    pub dump_ast: bool,
+    pub time: bool,
+    pub capabilities: Vec<Capability>,
+    pub iterations: usize,
+    pub warmup: usize,
+    pub lossy_utf8: bool,
+    pub max_source_size: usize,
+    pub color: bool,
+    pub seed: Option<u64>,
+    pub log_level: Option<log::Level>,
+    pub plugins: Vec<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub lcov: Option<PathBuf>,
+}
+
+/// Scripts larger than this are refused unless `--max-source-size` says otherwise.
+pub const DEFAULT_MAX_SOURCE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Parses the arguments following the program name.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options> {
+    let mut dump_tokens = false;
+    let mut dump_ast = false;
+    let mut time = false;
+    let mut capabilities = Vec::new();
+    let mut iterations = 10;
+    let mut warmup = 2;
+    let mut lossy_utf8 = false;
+    let mut max_source_size = DEFAULT_MAX_SOURCE_SIZE;
+    let mut color = true;
+    let mut seed = None;
+    let mut log_level = None;
+    let mut plugins = Vec::new();
+    let mut record = None;
+    let mut replay = None;
+    let mut lcov = None;
+    let mut positional = Vec::new();
+    let mut only_positional = false;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if only_positional || !arg.starts_with('-') || arg == "-" {
+            positional.push(arg);
+            continue;
+        }
+        match arg.as_str() {
+            "--" => only_positional = true,
+            "-h" | "--help" => return Ok(Options::new(Command::Help)),
+            "-V" | "--version" => return Ok(Options::new(Command::Version)),
+            "--dump-tokens" => dump_tokens = true,
+            "--dump-ast" => dump_ast = true,
+            "--time" => time = true,
+            "--allow-process" => capabilities.push(Capability::Process),
+            "--allow-net" => capabilities.push(Capability::Net),
+            "--allow-plugins" => capabilities.push(Capability::Plugins),
+            "--allow-files" => capabilities.push(Capability::Files),
+            "--plugin" => plugins.push(path_value(&arg, args.next())?),
+            "--record" => record = Some(path_value(&arg, args.next())?),
+            "--replay" => replay = Some(path_value(&arg, args.next())?),
+            "--lcov" => lcov = Some(path_value(&arg, args.next())?),
+            "--iterations" => iterations = count_value(&arg, args.next())?,
+            "--warmup" => warmup = count_value(&arg, args.next())?,
+            "--lossy-utf8" => lossy_utf8 = true,
+            "--max-source-size" => max_source_size = count_value(&arg, args.next())?,
+            "--no-color" => color = false,
+            "--seed" => seed = Some(count_value(&arg, args.next())?),
+            "--log-level" => log_level = Some(level_value(&arg, args.next())?),
+            _ => return Err(anyhow!("unknown option '{arg}'")),
+        }
+    }
+    let mut positional = positional.into_iter();
+    let command = match positional.next().as_deref() {
+        None | Some("repl") => Command::Repl,
+        Some("run") => Command::Run(script_value("run", positional.next())?),
+        Some("bench") => Command::Bench(script_value("bench", positional.next())?),
+        Some("graph") => Command::Graph(script_value("graph", positional.next())?),
+        Some("cfg") => Command::Cfg(script_value("cfg", positional.next())?),
+        Some("emit-js") => Command::EmitJs(script_value("emit-js", positional.next())?),
+        Some("cover") => Command::Cover(script_value("cover", positional.next())?),
+        Some(script) => Command::Run(script.into()),
+    };
+    if iterations == 0 {
+        return Err(anyhow!("'--iterations' must be at least 1"));
+    }
+    if record.is_some() && replay.is_some() {
+        return Err(anyhow!("'--record' and '--replay' cannot be used together"));
+    }
+    if let Some(extra) = positional.next() {
+        return Err(anyhow!("unexpected argument '{extra}'"));
+    }
+    Ok(Options {
+        command,
+        dump_tokens,
+        dump_ast,
+        time,
+        capabilities,
+        iterations,
+        warmup,
+        lossy_utf8,
+        max_source_size,
+        color,
+        seed,
+        log_level,
+        plugins,
+        record,
+        replay,
+        lcov,
+    })
+}
+
+fn script_value(command: &str, value: Option<String>) -> Result<PathBuf> {
+    value
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("'{command}' requires a script"))
+}
+
+fn path_value(option: &str, value: Option<String>) -> Result<PathBuf> {
+    value
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("'{option}' requires a path"))
+}
+
+fn count_value<T: FromStr>(option: &str, value: Option<String>) -> Result<T> {
+    let value = value.ok_or_else(|| anyhow!("'{option}' requires a number"))?;
+    value
+        .parse()
+        .map_err(|_| anyhow!("'{option}' expects a number, got '{value}'"))
+}
+
+fn level_value(option: &str, value: Option<String>) -> Result<log::Level> {
+    value
+        .ok_or_else(|| anyhow!("'{option}' requires a level"))?
+        .parse()
+        .map_err(|e| anyhow!("'{option}' {e}"))
+}
+
+impl Options {
+    const fn new(command: Command) -> Self {
+        Self {
+            command,
+            dump_tokens: false,
+            dump_ast: false,
+            time: false,
+            capabilities: Vec::new(),
+            iterations: 10,
+            warmup: 2,
+            lossy_utf8: false,
+            max_source_size: DEFAULT_MAX_SOURCE_SIZE,
+            color: true,
+            seed: None,
+            log_level: None,
+            plugins: Vec::new(),
+            record: None,
+            replay: None,
+            lcov: None,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::cli::Options;
+use anyhow::Result;
+use rox::interpreter::Interpreter;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+/// Runs a script with coverage on, then prints its annotated source and a summary to
+/// standard error, where they stay out of the script's own output. The report covers
+/// whatever ran, even if the script stopped with an error.
+pub fn run<P: AsRef<Path>>(
+    filename: P,
+    mut interpreter: Interpreter,
+    options: &Options,
+) -> Result<()> {
+    let filename = filename.as_ref();
+    let (contents, statements) = crate::parse_script(filename, options)?;
+    interpreter.enable_coverage();
+    let result = interpreter.interpret(&statements);
+    if let Some(coverage) = interpreter.coverage() {
+        let report = coverage.report(&statements, &contents);
+        eprint!("{}", report.annotate(&contents));
+        eprintln!(
+            "Lines: {} of {} ({})",
+            report.lines_hit(),
+            report.lines.len(),
+            percent(report.lines_hit(), report.lines.len())
+        );
+        eprintln!(
+            "Branches: {} of {} ({})",
+            report.branches_hit(),
+            report.branches.len() * 2,
+            percent(report.branches_hit(), report.branches.len() * 2)
+        );
+        if let Some(path) = &options.lcov {
+            fs::write(path, report.to_lcov(&filename.display().to_string()))
+                .map_err(|e| anyhow!("cannot write {}: {}", path.display(), e))?;
+        }
+    }
+    if let Err(error) = result {
+        crate::report_runtime_error(&error);
+        process::exit(70);
+    }
+    Ok(())
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn percent(hit: usize, total: usize) -> String {
+    if total == 0 {
+        "-".to_owned()
+    } else {
+        format!("{:.1}%", hit as f64 * 100.0 / total as f64)
+    }
+}
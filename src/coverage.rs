@@ -0,0 +1,151 @@
+//! Counting which statements and branches a run executes, for `rox cover`.
+
+use crate::ast::{Expr, Stmt, StmtKind};
+use crate::span::Span;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// Execution counts gathered while an interpreter runs with coverage on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    statements: HashMap<Span, u64>,
+    /// Keyed by the condition's span and which way it went.
+    branches: HashMap<(Span, bool), u64>,
+}
+
+/// Line coverage of a program, with the branches its `if`s and loops can take.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// How many times each line that starts a statement ran. Where a line starts several,
+    /// the one that ran most counts.
+    pub lines: BTreeMap<usize, u64>,
+    pub branches: Vec<Branch>,
+}
+
+/// A condition and how many times it was true and false.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Branch {
+    pub line: usize,
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+impl Coverage {
+    pub(crate) fn statement(&mut self, span: Span) {
+        *self.statements.entry(span).or_insert(0) += 1;
+    }
+
+    pub(crate) fn branch(&mut self, condition: Span, taken: bool) {
+        *self.branches.entry((condition, taken)).or_insert(0) += 1;
+    }
+
+    /// Reports on `statements`, the program that ran, which was parsed from `source`.
+    pub fn report(&self, statements: &[Stmt], source: &str) -> Report {
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let line = |span: Span| line_starts.partition_point(|&start| start <= span.start);
+        let mut report = Report::default();
+        let mut conditions = Vec::new();
+        let mut pending: Vec<&Stmt> = statements.iter().rev().collect();
+        while let Some(statement) = pending.pop() {
+            let hits = self.statements.get(&statement.span).copied().unwrap_or(0);
+            let count = report.lines.entry(line(statement.span)).or_insert(0);
+            *count = (*count).max(hits);
+            let mut children = Vec::new();
+            match &statement.kind {
+                StmtKind::Block(statements) => children.extend(statements),
+                StmtKind::DoWhile(body, condition) => {
+                    children.push(&**body);
+                    conditions.push(condition);
+                }
+                StmtKind::If(condition, then_branch, else_branch) => {
+                    conditions.push(condition);
+                    children.push(&**then_branch);
+                    children.extend(&**else_branch);
+                }
+                StmtKind::While(condition, body, _) => {
+                    conditions.push(condition);
+                    children.push(&**body);
+                }
+                StmtKind::Break(_)
+                | StmtKind::Continue(_)
+                | StmtKind::Expression(_)
+                | StmtKind::Print(_)
+                | StmtKind::Var(..) => {}
+            }
+            pending.extend(children.into_iter().rev());
+        }
+        report.branches = conditions
+            .into_iter()
+            .map(|condition: &Expr| Branch {
+                line: line(condition.span),
+                taken: self.branch_count(condition.span, true),
+                not_taken: self.branch_count(condition.span, false),
+            })
+            .collect();
+        report.branches.sort_by_key(|branch| branch.line);
+        report
+    }
+
+    fn branch_count(&self, condition: Span, taken: bool) -> u64 {
+        self.branches.get(&(condition, taken)).copied().unwrap_or(0)
+    }
+}
+
+impl Report {
+    pub fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|&&hits| hits > 0).count()
+    }
+
+    /// How many of the ways the branches could go were taken, two per condition.
+    pub fn branches_hit(&self) -> usize {
+        self.branches
+            .iter()
+            .map(|branch| usize::from(branch.taken > 0) + usize::from(branch.not_taken > 0))
+            .sum()
+    }
+
+    /// The source with each line led by how many times it ran, `#####` if it never did, or
+    /// `-` if no statement starts there, followed by the conditions that only went one way.
+    pub fn annotate(&self, source: &str) -> String {
+        let mut annotated = String::new();
+        for (i, text) in source.lines().enumerate() {
+            let count = match self.lines.get(&(i + 1)) {
+                Some(0) => "#####".to_owned(),
+                Some(hits) => hits.to_string(),
+                None => "-".to_owned(),
+            };
+            let _ = writeln!(annotated, "{count:>9} | {text}");
+        }
+        for branch in &self.branches {
+            let never = match (branch.taken, branch.not_taken) {
+                (0, 0) => "never ran",
+                (0, _) => "was never true",
+                (_, 0) => "was never false",
+                _ => continue,
+            };
+            let _ = writeln!(annotated, "The condition on line {} {never}.", branch.line);
+        }
+        annotated
+    }
+
+    /// The report as an lcov tracefile for the script at `path`.
+    pub fn to_lcov(&self, path: &str) -> String {
+        let mut lcov = format!("TN:\nSF:{path}\n");
+        for (block, branch) in self.branches.iter().enumerate() {
+            for (number, hits) in [branch.taken, branch.not_taken].iter().enumerate() {
+                let _ = writeln!(lcov, "BRDA:{},{block},{number},{hits}", branch.line);
+            }
+        }
+        let _ = writeln!(lcov, "BRF:{}", self.branches.len() * 2);
+        let _ = writeln!(lcov, "BRH:{}", self.branches_hit());
+        for (line, hits) in &self.lines {
+            let _ = writeln!(lcov, "DA:{line},{hits}");
+        }
+        let _ = writeln!(lcov, "LF:{}", self.lines.len());
+        let _ = writeln!(lcov, "LH:{}", self.lines_hit());
+        lcov.push_str("end_of_record\n");
+        lcov
+    }
+}
@@ -0,0 +1,74 @@
+use crate::span::Span;
+use crate::tokens::{Token, TokenType};
+use std::fmt;
+use std::num::NonZeroUsize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in the source before it runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub line: NonZeroUsize,
+    pub span: Span,
+    /// Where on the line the problem is, such as ` at 'x'` or ` at end`, or empty.
+    pub location: String,
+}
+
+impl Diagnostic {
+    pub fn error(line: NonZeroUsize, span: Span, message: &str) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.to_owned(),
+            line,
+            span,
+            location: String::new(),
+        }
+    }
+
+    pub fn error_at_token(token: &Token, message: &str) -> Self {
+        Self {
+            location: Self::location(token),
+            ..Self::error(token.line, token.span, message)
+        }
+    }
+
+    /// Reports a problem that does not stop the script from running.
+    pub fn warning_at_token(token: &Token, message: &str) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error_at_token(token, message)
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    fn location(token: &Token) -> String {
+        if token.type_ == TokenType::Eof {
+            " at end".to_owned()
+        } else {
+            format!(" at '{}'", token.lexeme)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        };
+        write!(
+            f,
+            "[line {}] {}{}: {}",
+            self.line, severity, self.location, self.message
+        )
+    }
+}
@@ -5,7 +5,7 @@ use std::collections::HashMap;
 
 #[derive(Clone, PartialEq, Debug, Default)]
 pub struct Environment {
-    enclosing: Option<Box<Environment>>,
+    enclosing: Option<Box<Self>>,
     values: HashMap<String, Value>,
 }
 
@@ -17,6 +17,42 @@ impl Environment {
         }
     }
 
+    pub fn take_enclosing(&mut self) -> Option<Self> {
+        self.enclosing.take().map(|enclosing| *enclosing)
+    }
+
+    pub fn enclosing(&self) -> Option<&Self> {
+        self.enclosing.as_deref()
+    }
+
+    /// The outermost scope, which holds the globals.
+    pub fn globals(&self) -> &Self {
+        self.enclosing
+            .as_ref()
+            .map_or(self, |enclosing| enclosing.globals())
+    }
+
+    pub fn globals_mut(&mut self) -> &mut Self {
+        // Matching on `&mut self.enclosing` would keep `self` borrowed in the `None` arm.
+        if self.enclosing.is_none() {
+            return self;
+        }
+        self.enclosing
+            .as_deref_mut()
+            .expect("checked above")
+            .globals_mut()
+    }
+
+    /// The bindings defined directly in this scope, in no particular order.
+    pub fn values(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.values.iter()
+    }
+
+    /// Looks `name` up in this scope only.
+    pub fn get_local(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
     pub fn define(&mut self, name: String, value: Value) {
         self.values.insert(name, value);
     }
@@ -32,11 +68,11 @@ impl Environment {
     }
 
     pub fn assign(&mut self, name: Token, value: Value) -> Result<()> {
-        if self.values.contains_key(&name.lexeme) {
-            self.values.insert(name.lexeme, value);
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
             Ok(())
-        } else if self.enclosing.is_some() {
-            self.enclosing.clone().unwrap().assign(name, value)
+        } else if let Some(enclosing) = &mut self.enclosing {
+            enclosing.assign(name, value)
         } else {
             Err(anyhow!(format!("Undefined variable '{}'.", name.lexeme)))
         }
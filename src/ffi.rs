@@ -0,0 +1,300 @@
+//! The C API declared in `include/rox.h`, for embedding rox in C and C++ programs.
+//!
+//! Every pointer passed in must be valid for the duration of the call, and every object
+//! returned by a `rox_*_new`-style function must be released with its matching `_free`.
+
+use crate::interpreter::{Exit, Interpreter};
+use crate::native::NativeFunction;
+use crate::value::Value;
+use anyhow::Result;
+use libloading::Library;
+use std::convert::TryFrom;
+use std::ffi::{c_void, CStr, CString};
+use std::mem;
+use std::os::raw::{c_char, c_int};
+use std::path::Path;
+use std::ptr;
+
+/// An interpreter plus the message from the last failed `rox_eval`.
+pub struct RoxVm {
+    interpreter: Interpreter,
+    last_error: CString,
+}
+
+/// A Lox value owned by the host.
+pub struct RoxValue(Value);
+
+/// What `rox_value_type` reports. Everything other than the scalar types is `Other`.
+#[repr(C)]
+pub enum RoxValueType {
+    Nil = 0,
+    Bool = 1,
+    Number = 2,
+    String = 3,
+    Other = 4,
+}
+
+/// A host function callable from Lox. It receives `argc` borrowed arguments and returns a
+/// new value, which rox takes ownership of, or null to raise a runtime error.
+pub type RoxFn = unsafe extern "C" fn(
+    user_data: *mut c_void,
+    args: *const *const RoxValue,
+    argc: usize,
+) -> *mut RoxValue;
+
+/// The entry point a plugin exports, `int rox_plugin_init(RoxVm *vm)`. It registers the
+/// plugin's natives and returns 0, or anything else if it could not.
+type PluginInit = unsafe extern "C" fn(vm: *mut RoxVm) -> c_int;
+
+/// Wraps the host's `user_data` so the closure owning it can be moved into a native.
+struct UserData(*mut c_void);
+
+/// Creates an interpreter with the standard natives and no capabilities.
+#[no_mangle]
+pub extern "C" fn rox_new() -> *mut RoxVm {
+    Box::into_raw(Box::new(RoxVm {
+        interpreter: Interpreter::new(),
+        last_error: CString::default(),
+    }))
+}
+
+/// # Safety
+///
+/// `vm` must come from `rox_new` and not be used afterwards. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn rox_free(vm: *mut RoxVm) {
+    if !vm.is_null() {
+        drop(Box::from_raw(vm));
+    }
+}
+
+/// Compiles and runs `source`, returning the exit status `rox run` would.
+///
+/// That is 0 on success, 65 if it did not compile, 70 after a runtime error, or the code
+/// passed to `exit()`. On failure `rox_last_error` describes what went wrong.
+///
+/// # Safety
+///
+/// `vm` must come from `rox_new` and `source` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rox_eval(vm: *mut RoxVm, source: *const c_char) -> c_int {
+    let vm = &mut *vm;
+    let source = CStr::from_ptr(source).to_string_lossy();
+    let (status, message) = match crate::parse(&source) {
+        Err(diagnostics) => {
+            let diagnostics: Vec<_> = diagnostics.iter().map(ToString::to_string).collect();
+            (65, diagnostics.join("\n"))
+        }
+        Ok(statements) => match vm.interpreter.interpret(&statements) {
+            Ok(()) => (0, String::new()),
+            Err(error) => match error.downcast_ref::<Exit>() {
+                Some(&Exit(code)) => (code, String::new()),
+                None => (70, error.to_string()),
+            },
+        },
+    };
+    vm.last_error = c_string(message);
+    status
+}
+
+/// Returns the error from the last `rox_eval`, or an empty string. The pointer is valid
+/// until the next call to `rox_eval` or `rox_free`.
+///
+/// # Safety
+///
+/// `vm` must come from `rox_new`.
+#[no_mangle]
+pub unsafe extern "C" fn rox_last_error(vm: *const RoxVm) -> *const c_char {
+    (*vm).last_error.as_ptr()
+}
+
+/// Defines a global native named `name` that calls `function` with `user_data`. It is
+/// global even when a plugin registers it while a script is inside a block.
+///
+/// `arity` is the exact number of arguments it takes, or -1 for any number. The name is
+/// copied and kept for the rest of the process.
+///
+/// # Safety
+///
+/// `vm` must come from `rox_new`, `name` must be a NUL-terminated string, and `function`
+/// must be safe to call with `user_data` for as long as the interpreter lives.
+#[no_mangle]
+pub unsafe extern "C" fn rox_register_fn(
+    vm: *mut RoxVm,
+    name: *const c_char,
+    arity: c_int,
+    function: RoxFn,
+    user_data: *mut c_void,
+) {
+    let vm = &mut *vm;
+    // Natives are named with `&'static str`s, and hosts register a handful of them.
+    let name: &'static str = Box::leak(
+        CStr::from_ptr(name)
+            .to_string_lossy()
+            .into_owned()
+            .into_boxed_str(),
+    );
+    let user_data = UserData(user_data);
+    let native = NativeFunction::with_closure(
+        name,
+        usize::try_from(arity).ok(),
+        move |_interpreter, arguments| {
+            let arguments: Vec<RoxValue> = arguments.iter().cloned().map(RoxValue).collect();
+            let pointers: Vec<*const RoxValue> = arguments.iter().map(ptr::from_ref).collect();
+            let result = function(user_data.0, pointers.as_ptr(), pointers.len());
+            if result.is_null() {
+                Err(anyhow!("{name}() failed."))
+            } else {
+                Ok(Box::from_raw(result).0)
+            }
+        },
+    );
+    vm.interpreter
+        .define_global(name, Value::NativeFunction(native));
+}
+
+/// Returns a copy of the global named `name`, or null if there is none.
+///
+/// # Safety
+///
+/// `vm` must come from `rox_new` and `name` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rox_get_global(vm: *const RoxVm, name: *const c_char) -> *mut RoxValue {
+    let name = CStr::from_ptr(name).to_string_lossy();
+    (*vm)
+        .interpreter
+        .global(&name)
+        .map_or(ptr::null_mut(), new_value)
+}
+
+#[no_mangle]
+pub extern "C" fn rox_value_nil() -> *mut RoxValue {
+    new_value(Value::Nil)
+}
+
+#[no_mangle]
+pub extern "C" fn rox_value_bool(b: bool) -> *mut RoxValue {
+    new_value(Value::Bool(b))
+}
+
+#[no_mangle]
+pub extern "C" fn rox_value_number(n: f64) -> *mut RoxValue {
+    new_value(Value::Number(n))
+}
+
+/// # Safety
+///
+/// `s` must be a NUL-terminated string. Invalid UTF-8 is replaced.
+#[no_mangle]
+pub unsafe extern "C" fn rox_value_string(s: *const c_char) -> *mut RoxValue {
+    new_value(Value::String_(
+        CStr::from_ptr(s).to_string_lossy().into_owned(),
+    ))
+}
+
+/// # Safety
+///
+/// `value` must come from rox and not be used afterwards. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn rox_value_free(value: *mut RoxValue) {
+    if !value.is_null() {
+        drop(Box::from_raw(value));
+    }
+}
+
+/// # Safety
+///
+/// `value` must be a valid value.
+#[no_mangle]
+pub const unsafe extern "C" fn rox_value_type(value: *const RoxValue) -> RoxValueType {
+    match (*value).0 {
+        Value::Nil => RoxValueType::Nil,
+        Value::Bool(_) => RoxValueType::Bool,
+        Value::Number(_) => RoxValueType::Number,
+        Value::String_(_) => RoxValueType::String,
+        _ => RoxValueType::Other,
+    }
+}
+
+/// Returns the boolean, or false if the value is not one.
+///
+/// # Safety
+///
+/// `value` must be a valid value.
+#[no_mangle]
+pub const unsafe extern "C" fn rox_value_as_bool(value: *const RoxValue) -> bool {
+    matches!((*value).0, Value::Bool(true))
+}
+
+/// Returns the number, or NaN if the value is not one.
+///
+/// # Safety
+///
+/// `value` must be a valid value.
+#[no_mangle]
+pub const unsafe extern "C" fn rox_value_as_number(value: *const RoxValue) -> f64 {
+    match (*value).0 {
+        Value::Number(n) => n,
+        _ => f64::NAN,
+    }
+}
+
+/// Returns the value as `print` would show it, in a new string to release with
+/// `rox_string_free`.
+///
+/// # Safety
+///
+/// `value` must be a valid value.
+#[no_mangle]
+pub unsafe extern "C" fn rox_value_to_string(value: *const RoxValue) -> *mut c_char {
+    c_string((*value).0.to_string()).into_raw()
+}
+
+/// # Safety
+///
+/// `s` must come from `rox_value_to_string` and not be used afterwards. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn rox_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+fn new_value(value: Value) -> *mut RoxValue {
+    Box::into_raw(Box::new(RoxValue(value)))
+}
+
+/// Converts to a C string, dropping any interior NUL bytes, which C cannot represent.
+fn c_string(s: String) -> CString {
+    CString::new(s).unwrap_or_else(|e| {
+        let mut bytes = e.into_vec();
+        bytes.retain(|&b| b != 0);
+        CString::new(bytes).unwrap_or_default()
+    })
+}
+
+/// Loads a plugin, a shared library whose `rox_plugin_init` registers natives through this
+/// API. It stays loaded for the rest of the process, since those natives call into it.
+pub fn load_plugin(interpreter: &mut Interpreter, path: &Path) -> Result<()> {
+    let display = path.display();
+    // SAFETY: loading a library runs its initializers and trusts its entry point to have
+    // the declared signature. Hosts only load plugins they were explicitly given.
+    let library = unsafe { Library::new(path) }
+        .map_err(|error| anyhow!("Cannot load plugin '{display}': {error}."))?;
+    let init = unsafe { library.get::<PluginInit>(b"rox_plugin_init\0") }
+        .map_err(|error| anyhow!("Plugin '{display}' has no rox_plugin_init: {error}."))?;
+    let mut vm = RoxVm {
+        interpreter: mem::take(interpreter),
+        last_error: CString::default(),
+    };
+    let status = unsafe { init(&raw mut vm) };
+    *interpreter = vm.interpreter;
+    mem::forget(library);
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Plugin '{display}' failed to initialize ({status})."
+        ))
+    }
+}
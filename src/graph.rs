@@ -0,0 +1,209 @@
+use crate::cli::Options;
+use anyhow::Result;
+use rox::ast::{Expr, ExprKind, Stmt, StmtKind};
+use rox::cfg::Cfg;
+use rox::span::Span;
+use rox::tokens::Literal;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Prints the parse tree of a script as a Graphviz DOT graph, one box per node labeled with
+/// its kind and source span.
+pub fn run<P: AsRef<Path>>(filename: P, options: &Options) -> Result<()> {
+    let (contents, statements) = crate::parse_script(filename.as_ref(), options)?;
+    let mut graph = Graph::default();
+    graph.node("program", Span::new(0, contents.len()));
+    for statement in &statements {
+        let child = graph.stmt(statement);
+        graph.edge(0, child, "");
+    }
+    write!(
+        io::stdout(),
+        "digraph ast {{\n  node [shape=box, fontname=\"monospace\"];\n{}}}\n",
+        graph.body
+    )?;
+    Ok(())
+}
+
+/// Prints the control-flow graph of a script as a Graphviz DOT graph, one box per basic
+/// block holding its code.
+pub fn cfg<P: AsRef<Path>>(filename: P, options: &Options) -> Result<()> {
+    let (contents, statements) = crate::parse_script(filename.as_ref(), options)?;
+    write!(
+        io::stdout(),
+        "{}",
+        Cfg::build(&statements).to_dot(&contents)
+    )?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct Graph {
+    body: String,
+    nodes: usize,
+}
+
+impl Graph {
+    /// Adds a node and returns its id.
+    fn node(&mut self, label: &str, span: Span) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+        let label = escape(&format!("{label}\n{span}"));
+        let _ = writeln!(self.body, "  n{id} [label=\"{label}\"];");
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: &str) {
+        if label.is_empty() {
+            let _ = writeln!(self.body, "  n{from} -> n{to};");
+        } else {
+            let _ = writeln!(self.body, "  n{from} -> n{to} [label=\"{label}\"];");
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> usize {
+        let (label, children): (String, Vec<(&str, Child)>) = match &stmt.kind {
+            StmtKind::Block(statements) => (
+                "block".to_owned(),
+                statements.iter().map(|s| ("", Child::Stmt(s))).collect(),
+            ),
+            StmtKind::Break(_) => ("break".to_owned(), Vec::new()),
+            StmtKind::Continue(_) => ("continue".to_owned(), Vec::new()),
+            StmtKind::DoWhile(body, condition) => (
+                "do while".to_owned(),
+                vec![
+                    ("body", Child::Stmt(body)),
+                    ("condition", Child::Expr(condition)),
+                ],
+            ),
+            StmtKind::Expression(expr) => ("expression".to_owned(), vec![("", Child::Expr(expr))]),
+            StmtKind::If(condition, then_branch, else_branch) => {
+                let mut children = vec![
+                    ("condition", Child::Expr(condition)),
+                    ("then", Child::Stmt(then_branch)),
+                ];
+                if let Some(else_branch) = else_branch.as_ref() {
+                    children.push(("else", Child::Stmt(else_branch)));
+                }
+                ("if".to_owned(), children)
+            }
+            StmtKind::Print(expr) => ("print".to_owned(), vec![("", Child::Expr(expr))]),
+            StmtKind::Var(name, initializer) => (
+                format!("var {}", name.lexeme),
+                initializer
+                    .iter()
+                    .map(|e| ("initializer", Child::Expr(e)))
+                    .collect(),
+            ),
+            StmtKind::While(condition, body, increment) => {
+                let mut children = vec![
+                    ("condition", Child::Expr(condition)),
+                    ("body", Child::Stmt(body)),
+                ];
+                if let Some(increment) = increment {
+                    children.push(("increment", Child::Expr(increment)));
+                }
+                ("while".to_owned(), children)
+            }
+        };
+        self.node_with_children(&label, stmt.span, children)
+    }
+
+    fn expr(&mut self, expr: &Expr) -> usize {
+        let (label, children): (String, Vec<(&str, Child)>) = match &expr.kind {
+            ExprKind::Assign(name, value) => (
+                format!("assign {}", name.lexeme),
+                vec![("value", Child::Expr(value))],
+            ),
+            ExprKind::Binary(left, operator, right) | ExprKind::Logical(left, operator, right) => (
+                operator.lexeme.clone(),
+                vec![("", Child::Expr(left)), ("", Child::Expr(right))],
+            ),
+            ExprKind::Call(callee, _, arguments) => {
+                let mut children = vec![("callee", Child::Expr(callee))];
+                children.extend(arguments.iter().map(|a| ("argument", Child::Expr(a))));
+                ("call".to_owned(), children)
+            }
+            ExprKind::Get(object, name) => (
+                format!(".{}", name.lexeme),
+                vec![("object", Child::Expr(object))],
+            ),
+            ExprKind::Global(name) => (format!("global {}", name.lexeme), Vec::new()),
+            ExprKind::Grouping(inner) => ("group".to_owned(), vec![("", Child::Expr(inner))]),
+            ExprKind::Index(object, _, index) => (
+                "index".to_owned(),
+                vec![
+                    ("object", Child::Expr(object)),
+                    ("index", Child::Expr(index)),
+                ],
+            ),
+            ExprKind::Literal(literal) => (literal_label(literal), Vec::new()),
+            ExprKind::Map(_, entries) => (
+                "map".to_owned(),
+                entries
+                    .iter()
+                    .flat_map(|(key, value)| {
+                        [("key", Child::Expr(key)), ("value", Child::Expr(value))]
+                    })
+                    .collect(),
+            ),
+            ExprKind::SetGlobal(name, value) => (
+                format!("assign global {}", name.lexeme),
+                vec![("value", Child::Expr(value))],
+            ),
+            ExprKind::SetIndex(object, _, index, value) => (
+                "set index".to_owned(),
+                vec![
+                    ("object", Child::Expr(object)),
+                    ("index", Child::Expr(index)),
+                    ("value", Child::Expr(value)),
+                ],
+            ),
+            ExprKind::Unary(operator, right) => {
+                (operator.lexeme.clone(), vec![("", Child::Expr(right))])
+            }
+            ExprKind::Variable(name) => (name.lexeme.clone(), Vec::new()),
+        };
+        self.node_with_children(&label, expr.span, children)
+    }
+
+    fn node_with_children(
+        &mut self,
+        label: &str,
+        span: Span,
+        children: Vec<(&str, Child)>,
+    ) -> usize {
+        let id = self.node(label, span);
+        for (role, child) in children {
+            let child_id = match child {
+                Child::Stmt(stmt) => self.stmt(stmt),
+                Child::Expr(expr) => self.expr(expr),
+            };
+            self.edge(id, child_id, role);
+        }
+        id
+    }
+}
+
+enum Child<'a> {
+    Stmt(&'a Stmt),
+    Expr(&'a Expr),
+}
+
+fn literal_label(literal: &Literal) -> String {
+    match literal {
+        Literal::String_(s) => format!("\"{s}\""),
+        Literal::Number(n) => n.to_string(),
+        Literal::BigInt(n) => format!("{n}n"),
+        Literal::Bool(b) => b.to_string(),
+        Literal::Nil => "nil".to_owned(),
+    }
+}
+
+/// Escapes text for a double-quoted DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,63 @@
+use rox::scanner::Scanner;
+use rox::span::Span;
+use rox::tokens::Token;
+use rox::tokens::TokenType::{
+    And, Break, Class, Continue, Do, Else, False, For, Fun, If, In, Loop, Nil, Number, Or, Print,
+    Return, String_, Super, This, True, Var, While,
+};
+
+const KEYWORD: &str = "\x1b[35m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[33m";
+const COMMENT: &str = "\x1b[90m";
+const RESET: &str = "\x1b[0m";
+
+/// Returns `source` with ANSI colors around its keywords, strings, numbers and comments.
+///
+/// The source is scanned afresh each time, so text that does not scan yet (such as an
+/// unterminated string) is left uncolored rather than throwing off what follows.
+pub fn highlight(source: &str) -> String {
+    let mut scanner = Scanner::with_trivia(source.to_owned());
+    let mut spans = Vec::new();
+    for token in scanner.scan_tokens() {
+        for trivia in token.leading_trivia.iter().chain(&token.trailing_trivia) {
+            spans.push((trivia.span, COMMENT));
+        }
+        if let Some(color) = color(&token) {
+            spans.push((token.span, color));
+        }
+    }
+    spans.sort_by_key(|(span, _)| span.start);
+
+    let mut highlighted = String::with_capacity(source.len());
+    let mut end = 0;
+    for (
+        Span {
+            start,
+            end: span_end,
+        },
+        color,
+    ) in spans
+    {
+        if start < end || source.get(start..span_end).is_none() {
+            continue;
+        }
+        highlighted.push_str(&source[end..start]);
+        highlighted.push_str(color);
+        highlighted.push_str(&source[start..span_end]);
+        highlighted.push_str(RESET);
+        end = span_end;
+    }
+    highlighted.push_str(&source[end..]);
+    highlighted
+}
+
+const fn color(token: &Token) -> Option<&'static str> {
+    match token.type_ {
+        And | Break | Class | Continue | Do | Else | False | For | Fun | If | In | Loop | Nil
+        | Or | Print | Return | Super | This | True | Var | While => Some(KEYWORD),
+        String_ => Some(STRING),
+        Number => Some(NUMBER),
+        _ => None,
+    }
+}
@@ -1,40 +1,524 @@
 use crate::ast::{Expr, ExprVisitor, Stmt, StmtVisitor};
+use crate::capability::Capability;
+use crate::coverage::Coverage;
 use crate::environment::Environment;
+use crate::log;
+use crate::map::Map;
+use crate::native::{self, NativeFunction};
+use crate::scope::Scope;
+use crate::stdlib;
 use crate::tokens::TokenType::{
-    self, Bang, BangEqual, EqualEqual, Greater, GreaterEqual, Less, LessEqual, Minus, Plus, Slash,
-    Star,
+    self, Bang, BangEqual, Comma, EqualEqual, Greater, GreaterEqual, GreaterGreater, In, Less,
+    LessEqual, LessLess, Minus, Plus, Slash, Star,
 };
 use crate::tokens::{Literal, Token};
 use crate::value::Value;
+use anyhow::{Error, Result};
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+use oorandom::Rand64;
+use rust_decimal::Decimal;
+use std::cell::{RefCell, RefMut};
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// An error raised while running a script, reported with the line it happened on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub message: String,
+    pub line: NonZeroUsize,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\n[line {}]", self.message, self.line)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// Unwinds the interpreter when a script calls `exit(code)`, so the host decides what to do
+/// with the requested process exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exit(pub i32);
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "exit({})", self.0)
+    }
+}
+
+impl std::error::Error for Exit {}
+
+/// Unwinds from a `break` or `continue` statement to the innermost enclosing loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LoopControl {
+    Break,
+    Continue,
+}
+
+impl fmt::Display for LoopControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Break => write!(f, "break"),
+            Self::Continue => write!(f, "continue"),
+        }
+    }
+}
+
+impl std::error::Error for LoopControl {}
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Interpreter {
     environment: Environment,
+    output: Output,
+    capabilities: HashSet<Capability>,
+    random: Rand64,
+    log_level: log::Level,
+    statements_executed: u64,
+    /// The globals `mock` replaced, in order, so `clear_mocks` can put them back.
+    mocked: Vec<(String, Value)>,
+    coverage: Option<Coverage>,
+}
+
+/// Where `print` output goes. Clones share the same destination.
+#[derive(Clone)]
+pub struct Output(Rc<RefCell<dyn Write>>);
+
+impl Output {
+    pub fn new<W: Write + 'static>(writer: W) -> Self {
+        Self(Rc::new(RefCell::new(writer)))
+    }
+}
+
+impl PartialEq for Output {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Output")
+    }
+}
+
+/// Seeds unseeded interpreters from the clock, so each run differs.
+fn time_seed() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos())
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Self::with_output(Output::new(io::stdout()))
+    }
+
+    /// Creates an interpreter whose `print` statement and print natives write to `output`
+    /// instead of standard output.
+    pub fn with_output(output: Output) -> Self {
+        let mut environment = Environment::default();
+        for function in native::globals() {
+            environment.define(function.name.to_owned(), Value::NativeFunction(function));
+        }
+        for namespace in stdlib::namespaces() {
+            environment.define(
+                namespace.name.to_owned(),
+                Value::Namespace(Rc::new(namespace)),
+            );
+        }
         Self {
-            environment: Environment::default(),
+            environment,
+            output,
+            capabilities: HashSet::new(),
+            random: Rand64::new(time_seed()),
+            log_level: log::Level::default(),
+            statements_executed: 0,
+            mocked: Vec::new(),
+            coverage: None,
         }
     }
 
-    pub fn interpret(&mut self, statements: &[Stmt]) {
-        for statement in statements {
-            self.execute(statement.clone());
+    /// Defines a global for every script this interpreter runs afterwards, such as a
+    /// host's own `NativeFunction` or `Namespace`. An existing global of that name is
+    /// replaced. Called from a native, it still defines a global, not a variable in the
+    /// scope the script is running in.
+    pub fn define_global(&mut self, name: &str, value: Value) {
+        self.environment
+            .globals_mut()
+            .define(name.to_owned(), value);
+    }
+
+    /// Returns the value of a global, such as one a script just defined.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.environment.globals().get_local(name).cloned()
+    }
+
+    /// Returns the native `name`, such as `random` or `Process.run`, if there is one.
+    pub fn native(&self, name: &str) -> Option<NativeFunction> {
+        let value = match name.split_once('.') {
+            Some((namespace, member)) => match self.global(namespace)? {
+                Value::Namespace(namespace) => namespace.get(member)?,
+                _ => return None,
+            },
+            None => self.global(name)?,
+        };
+        match value {
+            Value::NativeFunction(native) => Some(native),
+            _ => None,
         }
     }
 
-    fn execute_block(&mut self, statements: &[Stmt], environment: Environment) {
-        let previous = self.environment.clone();
-        self.environment = environment;
+    /// Replaces the native `name`, such as `random` or `Process.run`, with `function` until
+    /// `clear_mocks` is called, so scripts that read the time, random numbers or the outside
+    /// world can be tested deterministically. The mock takes the same arguments as the native.
+    pub fn mock<F>(&mut self, name: &str, function: F) -> Result<()>
+    where
+        F: Fn(&mut Self, &[Value]) -> Result<Value> + 'static,
+    {
+        let missing = || anyhow!("There is no native named '{name}' to mock.");
+        let (global, member) = match name.split_once('.') {
+            Some((global, member)) => (global, Some(member)),
+            None => (name, None),
+        };
+        let original = self.global(global).ok_or_else(missing)?;
+        let mock = |native: &NativeFunction| {
+            Value::NativeFunction(NativeFunction::with_closure(
+                native.name,
+                native.arity,
+                function,
+            ))
+        };
+        let replacement = match (&original, member) {
+            (Value::NativeFunction(native), None) => mock(native),
+            (Value::Namespace(namespace), Some(member)) => match namespace.get(member) {
+                Some(Value::NativeFunction(native)) => {
+                    Value::Namespace(Rc::new(namespace.with_member(native.name, mock(&native))))
+                }
+                _ => return Err(missing()),
+            },
+            _ => return Err(missing()),
+        };
+        self.mocked.push((global.to_owned(), original));
+        self.define_global(global, replacement);
+        Ok(())
+    }
+
+    /// Puts back every native `mock` replaced.
+    pub fn clear_mocks(&mut self) {
+        for (name, original) in std::mem::take(&mut self.mocked).into_iter().rev() {
+            self.define_global(&name, original);
+        }
+    }
+
+    /// Runs Lox source, typically helper definitions a host wants every script to see, in
+    /// the global environment. Compile errors are returned with all their diagnostics.
+    pub fn load_prelude(&mut self, source: &str) -> Result<()> {
+        let statements = crate::parse(source).map_err(|diagnostics| {
+            let diagnostics: Vec<_> = diagnostics.iter().map(ToString::to_string).collect();
+            anyhow!("The prelude did not compile:\n{}", diagnostics.join("\n"))
+        })?;
+        self.interpret(&statements)
+    }
+
+    /// Makes `random()` and `randomInt()` produce the same sequence on every run.
+    pub fn seed_random(&mut self, seed: u64) {
+        self.random = Rand64::new(u128::from(seed));
+    }
+
+    pub(crate) const fn random(&mut self) -> &mut Rand64 {
+        &mut self.random
+    }
+
+    /// Sets the least severe level `Log` writes messages at; `info` unless changed.
+    pub const fn set_log_level(&mut self, level: log::Level) {
+        self.log_level = level;
+    }
+
+    pub const fn log_level(&self) -> log::Level {
+        self.log_level
+    }
+
+    /// Grants scripts run by this interpreter the given capability.
+    pub fn allow(&mut self, capability: Capability) {
+        self.capabilities.insert(capability);
+    }
+
+    /// Fails unless `capability` has been granted; natives call this before touching the
+    /// outside world.
+    pub fn require(&self, capability: Capability, function: &str) -> Result<()> {
+        if self.capabilities.contains(&capability) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "{function}() needs the '{capability}' capability, which has not been granted."
+            ))
+        }
+    }
+
+    pub(crate) const fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    pub fn output(&self) -> RefMut<'_, dyn Write> {
+        self.output.0.borrow_mut()
+    }
+
+    /// Runs the statements in order, stopping at the first runtime error or `exit()` call.
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<()> {
         for statement in statements {
-            self.execute(statement.clone());
+            self.run_statement(statement.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Runs the statements in a scope of their own, nested in the globals, that starts out
+    /// with `scope`'s variables. Afterwards `scope` holds their final values along with any
+    /// variables the script declared at its top level, even if it stopped with an error.
+    pub fn run_with_scope(&mut self, statements: &[Stmt], scope: &mut Scope) -> Result<()> {
+        let globals = std::mem::take(&mut self.environment);
+        self.environment = Environment::new_from(globals);
+        for (name, value) in scope.iter() {
+            self.environment.define(name.to_owned(), value.clone());
+        }
+        let result = self.interpret(statements);
+        for (name, value) in self.environment.values() {
+            scope.set(name, value.clone());
+        }
+        self.environment = self.environment.take_enclosing().unwrap_or_default();
+        result
+    }
+
+    /// How many statements have run so far, counting each pass through a loop body.
+    pub const fn statements_executed(&self) -> u64 {
+        self.statements_executed
+    }
+
+    /// Starts counting which statements and branches run, from the next statement on.
+    pub fn enable_coverage(&mut self) {
+        self.coverage.get_or_insert_with(Coverage::default);
+    }
+
+    /// The counts gathered since `enable_coverage` was called, if it was.
+    pub const fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    fn run_statement(&mut self, statement: Stmt) -> Result<()> {
+        self.statements_executed += 1;
+        if let Some(coverage) = &mut self.coverage {
+            coverage.statement(statement.span);
         }
-        self.environment = previous;
+        self.execute(statement)
     }
 
-    const fn is_truthy(value: &Value) -> bool {
+    /// Evaluates the condition of an `if` or a loop, counting which way it went.
+    fn condition(&mut self, condition: Expr) -> Result<bool> {
+        let span = condition.span;
+        let taken = Self::is_truthy(&self.evaluate(condition)?);
+        if let Some(coverage) = &mut self.coverage {
+            coverage.branch(span, taken);
+        }
+        Ok(taken)
+    }
+
+    fn execute_block(&mut self, statements: &[Stmt]) -> Result<()> {
+        let enclosing = std::mem::take(&mut self.environment);
+        self.environment = Environment::new_from(enclosing);
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.run_statement(statement.clone()));
+        self.environment = self.environment.take_enclosing().unwrap_or_default();
+        result
+    }
+
+    /// Implements `needle in haystack`: a key lookup for maps and a substring search for
+    /// strings.
+    fn contains(operator: &Token, needle: &Value, haystack: &Value) -> Result<bool> {
+        match (needle, haystack) {
+            (_, Value::Map(map)) => map
+                .borrow()
+                .get(needle)
+                .map(|value| value.is_some())
+                .map_err(|e| Self::runtime_error(operator, e)),
+            (Value::String_(needle), Value::String_(haystack)) => Ok(haystack.contains(needle)),
+            (_, Value::String_(_)) => Err(Self::runtime_error(
+                operator,
+                "Only a string can be searched for in a string.",
+            )),
+            _ => Err(Self::runtime_error(
+                operator,
+                "Right operand of 'in' must be a map or a string.",
+            )),
+        }
+    }
+
+    /// Runs one pass of a loop body, returning whether the loop should keep going.
+    fn execute_loop_body(&mut self, body: &Stmt) -> Result<bool> {
+        match self.run_statement(body.clone()) {
+            Ok(()) => Ok(true),
+            Err(error) => match error.downcast_ref::<LoopControl>() {
+                Some(LoopControl::Break) => Ok(false),
+                Some(LoopControl::Continue) => Ok(true),
+                None => Err(error),
+            },
+        }
+    }
+
+    fn runtime_error(token: &Token, message: impl fmt::Display) -> Error {
+        RuntimeError {
+            message: message.to_string(),
+            line: token.line,
+        }
+        .into()
+    }
+
+    fn number_operands(operator: &Token, left: &Value, right: &Value) -> Result<(f64, f64)> {
+        Self::check_unmixed(operator, left, right)?;
+        if let (Value::Number(l), Value::Number(r)) = (left, right) {
+            Ok((*l, *r))
+        } else {
+            Err(Self::runtime_error(operator, "Operands must be numbers."))
+        }
+    }
+
+    /// Rejects arithmetic between two different numeric types, which would otherwise lose
+    /// precision silently.
+    fn check_unmixed(operator: &Token, left: &Value, right: &Value) -> Result<()> {
+        if left.is_numeric() && right.is_numeric() && left.type_name() != right.type_name() {
+            Err(Self::runtime_error(
+                operator,
+                format!(
+                    "Cannot mix {} and {} operands; convert one of them first.",
+                    left.type_name(),
+                    right.type_name()
+                ),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Shifts an integer left or right. Operands and the result must be integers no larger than
+    /// 2^53 in magnitude, the range numbers represent exactly.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn shift(operator: &Token, left: f64, right: f64) -> Result<Value> {
+        const LIMIT: f64 = 9_007_199_254_740_992.0;
+        if left.fract() != 0.0 || right.fract() != 0.0 || left.abs() > LIMIT {
+            return Err(Self::runtime_error(
+                operator,
+                "Shift operands must be integers within +/-2^53.",
+            ));
+        }
+        if !(0.0..=53.0).contains(&right) {
+            return Err(Self::runtime_error(
+                operator,
+                "Shift amount must be between 0 and 53.",
+            ));
+        }
+        let (left, right) = (left as i64, right as u32);
+        let shifted = if operator.type_ == LessLess {
+            left << right
+        } else {
+            left >> right
+        };
+        if shifted.unsigned_abs() > 1 << 53 {
+            return Err(Self::runtime_error(
+                operator,
+                "Shift result is out of range.",
+            ));
+        }
+        Ok(Value::Number(shifted as f64))
+    }
+
+    /// Applies an arithmetic or comparison operator to two decimals.
+    fn decimal_binary(operator: &Token, left: Decimal, right: Decimal) -> Result<Value> {
+        let overflow = || Self::runtime_error(operator, "Decimal arithmetic overflowed.");
+        Ok(match operator.type_ {
+            Minus => Value::Decimal(left.checked_sub(right).ok_or_else(overflow)?),
+            Plus => Value::Decimal(left.checked_add(right).ok_or_else(overflow)?),
+            Star => Value::Decimal(left.checked_mul(right).ok_or_else(overflow)?),
+            Slash if right.is_zero() => {
+                return Err(Self::runtime_error(operator, "Division by zero."))
+            }
+            Slash => Value::Decimal(left.checked_div(right).ok_or_else(overflow)?),
+            Greater => Value::Bool(left > right),
+            GreaterEqual => Value::Bool(left >= right),
+            Less => Value::Bool(left < right),
+            LessEqual => Value::Bool(left <= right),
+            LessLess | GreaterGreater => {
+                return Err(Self::runtime_error(
+                    operator,
+                    "Shift operands must be integers.",
+                ))
+            }
+            BangEqual => Value::Bool(left != right),
+            EqualEqual => Value::Bool(left == right),
+            _ => return Err(Self::unknown_operator(operator)),
+        })
+    }
+
+    /// Applies an arithmetic or comparison operator to two bigints.
+    fn bigint_binary(operator: &Token, left: &BigInt, right: &BigInt) -> Result<Value> {
+        Ok(match operator.type_ {
+            Minus => Value::BigInt(left - right),
+            Plus => Value::BigInt(left + right),
+            Star => Value::BigInt(left * right),
+            Slash if right.is_zero() => {
+                return Err(Self::runtime_error(operator, "Division by zero."))
+            }
+            Slash => Value::BigInt(left / right),
+            LessLess | GreaterGreater => {
+                // Bigints have no width to stop at, so the limit keeps `1n << n` from
+                // asking for gigabytes.
+                const LIMIT: usize = 1 << 20;
+                let Some(amount) = right.to_usize().filter(|&amount| amount <= LIMIT) else {
+                    return Err(Self::runtime_error(
+                        operator,
+                        format!("Shift amount must be between 0n and {LIMIT}n."),
+                    ));
+                };
+                Value::BigInt(if operator.type_ == LessLess {
+                    left << amount
+                } else {
+                    left >> amount
+                })
+            }
+            Greater => Value::Bool(left > right),
+            GreaterEqual => Value::Bool(left >= right),
+            Less => Value::Bool(left < right),
+            LessEqual => Value::Bool(left <= right),
+            BangEqual => Value::Bool(left != right),
+            EqualEqual => Value::Bool(left == right),
+            _ => return Err(Self::unknown_operator(operator)),
+        })
+    }
+
+    /// Reports a binary operator the interpreter has no case for, rather than quietly
+    /// producing nil.
+    fn unknown_operator(operator: &Token) -> Error {
+        Self::runtime_error(
+            operator,
+            format!("Unknown binary operator '{}'.", operator.lexeme),
+        )
+    }
+
+    pub(crate) const fn is_truthy(value: &Value) -> bool {
         if let Value::Bool(b) = value {
             *b
         } else {
@@ -42,166 +526,363 @@ impl Interpreter {
         }
     }
 
-    fn is_equal(a: Value, b: Value) -> bool {
+    /// Numbers compare exactly, as IEEE 754 says: NaN equals nothing, itself included, and
+    /// `0.1 + 0.2 == 0.3` is false. The ordering operators likewise are all false for NaN.
+    pub(crate) fn is_equal(a: &Value, b: &Value) -> bool {
+        Self::is_equal_in(a, b, &mut Vec::new())
+    }
+
+    /// Compares maps structurally: they are equal if they have the same keys with equal
+    /// values, in any order. `comparing` holds the pairs of maps already being compared
+    /// further up, which are assumed equal so that cyclic maps terminate.
+    fn is_equal_in(
+        a: &Value,
+        b: &Value,
+        comparing: &mut Vec<(*const RefCell<Map>, *const RefCell<Map>)>,
+    ) -> bool {
         match (a, b) {
             (Value::Nil, Value::Nil) => true,
             (Value::Bool(a), Value::Bool(b)) => a == b,
             (Value::String_(a), Value::String_(b)) => a == b,
-            (Value::Number(a), Value::Number(b)) => (a - b).abs() < std::f64::EPSILON,
+            #[allow(clippy::float_cmp)]
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::BigInt(a), Value::BigInt(b)) => a == b,
+            (Value::Decimal(a), Value::Decimal(b)) => a == b,
+            (Value::NativeFunction(a), Value::NativeFunction(b)) => a == b,
+            (Value::Namespace(a), Value::Namespace(b)) => Rc::ptr_eq(a, b),
+            (Value::Handle(a), Value::Handle(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => {
+                let pair = (Rc::as_ptr(a), Rc::as_ptr(b));
+                if Rc::ptr_eq(a, b) || comparing.contains(&pair) {
+                    return true;
+                }
+                let (a, b) = (a.borrow(), b.borrow());
+                if a.len() != b.len() {
+                    return false;
+                }
+                comparing.push(pair);
+                let equal = a.iter().all(|(key, a_value)| {
+                    matches!(b.get(key), Ok(Some(b_value))
+                        if Self::is_equal_in(a_value, b_value, comparing))
+                });
+                comparing.pop();
+                equal
+            }
             _ => false,
         }
     }
 }
 
-impl StmtVisitor<()> for Interpreter {
-    fn visit_while_stmt(&mut self, condition: Expr, body: Box<Stmt>) {
-        while Self::is_truthy(&self.evaluate(condition.clone())) {
-            self.execute(*body.clone());
+impl StmtVisitor<Result<()>> for Interpreter {
+    fn visit_while_stmt(
+        &mut self,
+        condition: Expr,
+        body: Box<Stmt>,
+        increment: Option<Expr>,
+    ) -> Result<()> {
+        while self.condition(condition.clone())? {
+            if !self.execute_loop_body(&body)? {
+                break;
+            }
+            if let Some(increment) = &increment {
+                self.evaluate(increment.clone())?;
+            }
+        }
+        Ok(())
+    }
+    fn visit_do_while_stmt(&mut self, body: Box<Stmt>, condition: Expr) -> Result<()> {
+        while self.execute_loop_body(&body)? {
+            if !self.condition(condition.clone())? {
+                break;
+            }
         }
+        Ok(())
+    }
+    fn visit_break_stmt(&mut self, _keyword: Token) -> Result<()> {
+        Err(LoopControl::Break.into())
+    }
+    fn visit_continue_stmt(&mut self, _keyword: Token) -> Result<()> {
+        Err(LoopControl::Continue.into())
     }
     fn visit_if_stmt(
         &mut self,
         condition: Expr,
         then_branch: Box<Stmt>,
         else_branch: Box<Option<Stmt>>,
-    ) {
-        if Self::is_truthy(&self.evaluate(condition)) {
-            self.execute(*then_branch);
+    ) -> Result<()> {
+        if self.condition(condition)? {
+            self.run_statement(*then_branch)
         } else if let Some(else_branch) = *else_branch {
-            self.execute(else_branch);
+            self.run_statement(else_branch)
+        } else {
+            Ok(())
         }
     }
-    fn visit_block_stmt(&mut self, statements: Vec<Stmt>) {
-        self.execute_block(&statements, Environment::new_from(self.environment.clone()));
+    fn visit_block_stmt(&mut self, statements: Vec<Stmt>) -> Result<()> {
+        self.execute_block(&statements)
     }
 
-    fn visit_expression_stmt(&mut self, stmt: Expr) {
-        self.evaluate(stmt);
+    fn visit_expression_stmt(&mut self, stmt: Expr) -> Result<()> {
+        self.evaluate(stmt)?;
+        Ok(())
     }
 
-    fn visit_print_stmt(&mut self, stmt: Expr) {
-        let value = self.evaluate(stmt);
-        println!("{}", value);
+    fn visit_print_stmt(&mut self, stmt: Expr) -> Result<()> {
+        let value = self.evaluate(stmt)?;
+        writeln!(self.output(), "{value}")?;
+        Ok(())
     }
 
-    fn visit_var_stmt(&mut self, name: Token, initializer: Option<Expr>) {
-        let value =
-            initializer.map_or_else(|| Value::Nil, |initializer| self.evaluate(initializer));
+    fn visit_var_stmt(&mut self, name: Token, initializer: Option<Expr>) -> Result<()> {
+        let value = match initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
         self.environment.define(name.lexeme, value);
+        Ok(())
     }
 }
 
-impl ExprVisitor<Value> for Interpreter {
-    fn visit_logical_expr(&mut self, left: Box<Expr>, operator: Token, right: Box<Expr>) -> Value {
-        let left = self.evaluate(*left);
+impl ExprVisitor<Result<Value>> for Interpreter {
+    fn visit_logical_expr(
+        &mut self,
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    ) -> Result<Value> {
+        let left = self.evaluate(*left)?;
         if operator.type_ == TokenType::Or {
             if Self::is_truthy(&left) {
-                return left;
+                return Ok(left);
             }
         } else if !Self::is_truthy(&left) {
-            return left;
+            return Ok(left);
         }
         self.evaluate(*right)
     }
-    fn visit_assign_expr(&mut self, name: Token, value: Box<Expr>) -> Value {
-        let value = self.evaluate(*value);
-        self.environment.assign(name, value.clone()).unwrap();
-        value
+    fn visit_assign_expr(&mut self, name: Token, value: Box<Expr>) -> Result<Value> {
+        let value = self.evaluate(*value)?;
+        self.environment
+            .assign(name.clone(), value.clone())
+            .map_err(|e| Self::runtime_error(&name, e))?;
+        Ok(value)
     }
 
-    fn visit_binary_expr(&mut self, left: Box<Expr>, operator: Token, right: Box<Expr>) -> Value {
-        let left = self.evaluate(*left);
-        let right = self.evaluate(*right);
+    fn visit_binary_expr(
+        &mut self,
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    ) -> Result<Value> {
+        let left = self.evaluate(*left)?;
+        let right = self.evaluate(*right)?;
         match operator.type_ {
+            Comma => return Ok(right),
+            In => return Self::contains(&operator, &left, &right).map(Value::Bool),
+            _ => {}
+        }
+        if let (Value::BigInt(l), Value::BigInt(r)) = (&left, &right) {
+            return Self::bigint_binary(&operator, l, r);
+        }
+        if let (Value::Decimal(l), Value::Decimal(r)) = (&left, &right) {
+            return Self::decimal_binary(&operator, *l, *r);
+        }
+        // UTF-8 byte order is the same as code point order, so strings compare directly.
+        if let (Value::String_(l), Value::String_(r)) = (&left, &right) {
+            match operator.type_ {
+                Greater => return Ok(Value::Bool(l > r)),
+                GreaterEqual => return Ok(Value::Bool(l >= r)),
+                Less => return Ok(Value::Bool(l < r)),
+                LessEqual => return Ok(Value::Bool(l <= r)),
+                _ => {}
+            }
+        }
+        Ok(match operator.type_ {
             Minus => {
-                if let (Value::Number(l), Value::Number(r)) = (left.clone(), right.clone()) {
-                    Value::Number(l - r)
-                } else {
-                    panic!("{:?} and {:?} must be numbers", left, right);
-                }
+                let (l, r) = Self::number_operands(&operator, &left, &right)?;
+                Value::Number(l - r)
             }
             Slash => {
-                if let (Value::Number(l), Value::Number(r)) = (left.clone(), right.clone()) {
-                    Value::Number(l / r)
-                } else {
-                    panic!("{:?} and {:?} must be numbers", left, right);
-                }
+                let (l, r) = Self::number_operands(&operator, &left, &right)?;
+                Value::Number(l / r)
             }
             Star => {
-                if let (Value::Number(l), Value::Number(r)) = (left.clone(), right.clone()) {
-                    Value::Number(l * r)
-                } else {
-                    panic!("{:?} and {:?} must be numbers", left, right);
-                }
+                let (l, r) = Self::number_operands(&operator, &left, &right)?;
+                Value::Number(l * r)
             }
-            Plus => {
-                if let (Value::Number(l), Value::Number(r)) = (left.clone(), right.clone()) {
-                    Value::Number(l + r)
-                } else if let (Value::String_(l), Value::String_(r)) = (left.clone(), right.clone())
-                {
-                    Value::String_(l + &r)
-                } else {
-                    panic!(
-                        "{:?} and {:?} must both be numbers or both be strings",
-                        left, right
-                    );
+            Plus => match (left, right) {
+                (Value::Number(l), Value::Number(r)) => Value::Number(l + r),
+                (Value::String_(l), Value::String_(r)) => Value::String_(l + &r),
+                (left, right) => {
+                    Self::check_unmixed(&operator, &left, &right)?;
+                    return Err(Self::runtime_error(
+                        &operator,
+                        "Operands must be two numbers or two strings.",
+                    ));
                 }
+            },
+            LessLess | GreaterGreater => {
+                let (l, r) = Self::number_operands(&operator, &left, &right)?;
+                Self::shift(&operator, l, r)?
             }
             Greater => {
-                if let (Value::Number(l), Value::Number(r)) = (left.clone(), right.clone()) {
-                    Value::Bool(l > r)
-                } else {
-                    panic!("{:?} and {:?} must be numbers", left, right);
-                }
+                let (l, r) = Self::number_operands(&operator, &left, &right)?;
+                Value::Bool(l > r)
             }
             GreaterEqual => {
-                if let (Value::Number(l), Value::Number(r)) = (left.clone(), right.clone()) {
-                    Value::Bool(l >= r)
-                } else {
-                    panic!("{:?} and {:?} must be numbers", left, right);
-                }
+                let (l, r) = Self::number_operands(&operator, &left, &right)?;
+                Value::Bool(l >= r)
             }
             Less => {
-                if let (Value::Number(l), Value::Number(r)) = (left.clone(), right.clone()) {
-                    Value::Bool(l < r)
-                } else {
-                    panic!("{:?} and {:?} must be numbers", left, right);
-                }
+                let (l, r) = Self::number_operands(&operator, &left, &right)?;
+                Value::Bool(l < r)
             }
             LessEqual => {
-                if let (Value::Number(l), Value::Number(r)) = (left.clone(), right.clone()) {
-                    Value::Bool(l <= r)
-                } else {
-                    panic!("{:?} and {:?} must be numbers", left, right);
-                }
+                let (l, r) = Self::number_operands(&operator, &left, &right)?;
+                Value::Bool(l <= r)
             }
-            BangEqual => Value::Bool(!Self::is_equal(left, right)),
-            EqualEqual => Value::Bool(Self::is_equal(left, right)),
-            _ => Value::Nil,
+            BangEqual => Value::Bool(!Self::is_equal(&left, &right)),
+            EqualEqual => Value::Bool(Self::is_equal(&left, &right)),
+            _ => return Err(Self::unknown_operator(&operator)),
+        })
+    }
+    fn visit_call_expr(
+        &mut self,
+        callee: Box<Expr>,
+        paren: Token,
+        arguments: Vec<Expr>,
+    ) -> Result<Value> {
+        let callee = self.evaluate(*callee)?;
+        let arguments = arguments
+            .into_iter()
+            .map(|argument| self.evaluate(argument))
+            .collect::<Result<Vec<_>>>()?;
+        let Value::NativeFunction(function) = callee else {
+            return Err(Self::runtime_error(
+                &paren,
+                "Can only call functions and classes.",
+            ));
+        };
+        if let Some(arity) = function.arity {
+            if arguments.len() != arity {
+                return Err(Self::runtime_error(
+                    &paren,
+                    format!("Expected {} arguments but got {}.", arity, arguments.len()),
+                ));
+            }
+        }
+        function.call(self, &arguments).map_err(|error| {
+            if error.is::<Exit>() || error.is::<RuntimeError>() {
+                error
+            } else {
+                Self::runtime_error(&paren, error)
+            }
+        })
+    }
+
+    fn visit_get_expr(&mut self, object: Box<Expr>, name: Token) -> Result<Value> {
+        match self.evaluate(*object)? {
+            Value::Namespace(namespace) => namespace.get(&name.lexeme).ok_or_else(|| {
+                Self::runtime_error(
+                    &name,
+                    format!(
+                        "Undefined property '{}' on {}.",
+                        name.lexeme, namespace.name
+                    ),
+                )
+            }),
+            _ => Err(Self::runtime_error(
+                &name,
+                "Only namespaces have properties.",
+            )),
         }
     }
-    fn visit_grouping_expr(&mut self, expression: Box<Expr>) -> Value {
+
+    fn visit_global_expr(&mut self, name: Token) -> Result<Value> {
+        self.environment
+            .globals()
+            .get(&name)
+            .map_err(|e| Self::runtime_error(&name, e))
+    }
+
+    fn visit_grouping_expr(&mut self, expression: Box<Expr>) -> Result<Value> {
         self.evaluate(*expression)
     }
-    fn visit_literal_expr(&mut self, value: Literal) -> Value {
-        value.into()
+    fn visit_index_expr(
+        &mut self,
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    ) -> Result<Value> {
+        let object = self.evaluate(*object)?;
+        let index = self.evaluate(*index)?;
+        if let Value::Map(map) = object {
+            let map = map.borrow();
+            let value = map
+                .get(&index)
+                .map_err(|e| Self::runtime_error(&bracket, e))?;
+            Ok(value.cloned().unwrap_or(Value::Nil))
+        } else {
+            Err(Self::runtime_error(&bracket, "Only maps can be indexed."))
+        }
     }
-    fn visit_unary_expr(&mut self, operator: Token, right: Box<Expr>) -> Value {
-        let right = self.evaluate(*right);
-        match operator.type_ {
-            Minus => {
-                if let Value::Number(n) = right {
-                    Value::Number(-n)
-                } else {
-                    panic!("{:?} must be a number", right);
-                }
-            }
+    fn visit_map_expr(&mut self, brace: Token, entries: Vec<(Expr, Expr)>) -> Result<Value> {
+        let mut map = Map::new();
+        for (key, value) in entries {
+            let key = self.evaluate(key)?;
+            let value = self.evaluate(value)?;
+            map.insert(key, value)
+                .map_err(|e| Self::runtime_error(&brace, e))?;
+        }
+        Ok(Value::map(map))
+    }
+    fn visit_set_global_expr(&mut self, name: Token, value: Box<Expr>) -> Result<Value> {
+        let value = self.evaluate(*value)?;
+        self.environment
+            .globals_mut()
+            .assign(name.clone(), value.clone())
+            .map_err(|e| Self::runtime_error(&name, e))?;
+        Ok(value)
+    }
+    fn visit_set_index_expr(
+        &mut self,
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    ) -> Result<Value> {
+        let object = self.evaluate(*object)?;
+        let index = self.evaluate(*index)?;
+        let value = self.evaluate(*value)?;
+        if let Value::Map(map) = object {
+            map.borrow_mut()
+                .insert(index, value.clone())
+                .map_err(|e| Self::runtime_error(&bracket, e))?;
+            Ok(value)
+        } else {
+            Err(Self::runtime_error(&bracket, "Only maps can be indexed."))
+        }
+    }
+    fn visit_literal_expr(&mut self, value: Literal) -> Result<Value> {
+        Ok(value.into())
+    }
+    fn visit_unary_expr(&mut self, operator: Token, right: Box<Expr>) -> Result<Value> {
+        let right = self.evaluate(*right)?;
+        Ok(match operator.type_ {
+            Minus => match right {
+                Value::Number(n) => Value::Number(-n),
+                Value::BigInt(n) => Value::BigInt(-n),
+                Value::Decimal(n) => Value::Decimal(-n),
+                _ => return Err(Self::runtime_error(&operator, "Operand must be a number.")),
+            },
             Bang => Value::Bool(!Self::is_truthy(&right)),
             _ => Value::Nil,
-        }
+        })
     }
 
-    fn visit_variable_expr(&self, name: Token) -> Value {
-        self.environment.get(&name).unwrap()
+    fn visit_variable_expr(&self, name: Token) -> Result<Value> {
+        self.environment
+            .get(&name)
+            .map_err(|e| Self::runtime_error(&name, e))
     }
 }
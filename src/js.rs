@@ -0,0 +1,412 @@
+use crate::ast::{Expr, ExprKind, Stmt, StmtKind};
+use crate::diagnostic::Diagnostic;
+use crate::native;
+use crate::stdlib;
+use crate::tokens::Literal;
+use crate::tokens::Token;
+use crate::tokens::TokenType::{
+    Bang, BangEqual, Comma, EqualEqual, Greater, GreaterEqual, GreaterGreater, In, Less, LessEqual,
+    LessLess, Minus, Or,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write;
+
+/// The runtime every emitted program starts with: `$lox` holds the helpers for the places
+/// Lox and JavaScript disagree, such as truthiness and map equality, and `$globals` the
+/// natives.
+const PRELUDE: &str = include_str!("js_prelude.js");
+
+/// The natives and namespaces `natives` in the prelude defines. Keep the two in step.
+const JS_NATIVES: &[&str] = &[
+    "print",
+    "println",
+    "type",
+    "arity",
+    "name",
+    "toFixed",
+    "toPrecision",
+    "toHex",
+    "toBinary",
+    "fromCharCode",
+    "parseInt",
+    "parseFloat",
+    "isNaN",
+    "isFinite",
+    "exit",
+    "bigint",
+    "freeze",
+    "isFrozen",
+    "has",
+    "delete",
+    "clone",
+    "deepCopy",
+    "random",
+    "randomInt",
+    "Number",
+];
+
+/// Words JavaScript reserves or gives special meaning that are valid Lox identifiers.
+const RESERVED: &[&str] = &[
+    "arguments",
+    "await",
+    "case",
+    "catch",
+    "const",
+    "debugger",
+    "default",
+    "delete",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "finally",
+    "function",
+    "implements",
+    "import",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "static",
+    "switch",
+    "throw",
+    "try",
+    "typeof",
+    "undefined",
+    "void",
+    "with",
+    "yield",
+];
+
+/// Translates a resolved program to a standalone JavaScript file that runs the same way
+/// under Node or in a browser, printing with `console.log`.
+///
+/// The output mirrors the source statement for statement. Some runtime errors rox reports,
+/// such as adding a number to a string, are not checked, and very large and very small
+/// numbers print in exponent form. A program using a native the prelude lacks, such as
+/// `decimal` or `Http`, is refused with an error at each use.
+pub fn emit(statements: &[Stmt]) -> Result<String, Vec<Diagnostic>> {
+    let rox_natives = native::globals()
+        .iter()
+        .map(|function| function.name)
+        .chain(stdlib::namespaces().iter().map(|namespace| namespace.name))
+        .collect();
+    let mut emitter = Emitter {
+        out: String::new(),
+        indent: 1,
+        scopes: vec![HashSet::new()],
+        shadowed_globals: RefCell::default(),
+        rox_natives,
+        diagnostics: RefCell::default(),
+    };
+    for statement in statements {
+        emitter.statement(statement);
+    }
+    // Function declarations are hoisted, so these work from anywhere above them.
+    for name in emitter.shadowed_globals.take() {
+        let name = js_name(&name);
+        emitter.line(&format!("function $global_{name}() {{ return {name}; }}"));
+        emitter.line(&format!(
+            "function $set_global_{name}(value) {{ return {name} = value; }}"
+        ));
+    }
+    let diagnostics = emitter.diagnostics.take();
+    if diagnostics.is_empty() {
+        Ok(format!(
+            "{PRELUDE}\n$lox.run(() => {{\n{}}});\n",
+            emitter.out
+        ))
+    } else {
+        Err(diagnostics)
+    }
+}
+
+struct Emitter {
+    out: String,
+    indent: usize,
+    /// The variables declared in each enclosing block; anything else is a native.
+    scopes: Vec<HashSet<String>>,
+    /// The program's globals that `global.name` reaches from where a local shadows them,
+    /// which only accessor functions declared outside every block can do.
+    shadowed_globals: RefCell<BTreeSet<String>>,
+    /// Every native rox defines, to tell them from globals the program never declares.
+    rox_natives: HashSet<&'static str>,
+    diagnostics: RefCell<Vec<Diagnostic>>,
+}
+
+impl Emitter {
+    fn line(&mut self, text: &str) {
+        let _ = writeln!(self.out, "{:width$}{text}", "", width = self.indent * 2);
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Block(statements) => {
+                self.line("{");
+                self.block_body(statements);
+                self.line("}");
+            }
+            StmtKind::Break(_) => self.line("break;"),
+            StmtKind::Continue(_) => self.line("continue;"),
+            StmtKind::DoWhile(body, condition) => {
+                self.line("do {");
+                self.body(body);
+                let condition = self.condition(condition);
+                self.line(&format!("}} while ({condition});"));
+            }
+            StmtKind::Expression(expr) => {
+                let expr = self.expr(expr);
+                self.line(&format!("{expr};"));
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                let condition = self.condition(condition);
+                self.line(&format!("if ({condition}) {{"));
+                self.body(then_branch);
+                let mut else_branch = else_branch.as_ref().as_ref();
+                // Chain `else if` rather than nesting each `if` a level deeper.
+                while let Some(stmt) = else_branch {
+                    if let StmtKind::If(condition, then_branch, next) = &stmt.kind {
+                        let condition = self.condition(condition);
+                        self.line(&format!("}} else if ({condition}) {{"));
+                        self.body(then_branch);
+                        else_branch = next.as_ref().as_ref();
+                    } else {
+                        self.line("} else {");
+                        self.body(stmt);
+                        else_branch = None;
+                    }
+                }
+                self.line("}");
+            }
+            StmtKind::Print(expr) => {
+                let expr = self.expr(expr);
+                self.line(&format!("$lox.print({expr});"));
+            }
+            StmtKind::Var(name, initializer) => {
+                let value = initializer
+                    .as_ref()
+                    .map_or_else(|| "null".to_owned(), |expr| self.expr(expr));
+                // Lox lets globals be redeclared, which only `var` allows.
+                let keyword = if self.scopes.len() == 1 { "var" } else { "let" };
+                let name = &name.lexeme;
+                self.line(&format!("{keyword} {} = {value};", js_name(name)));
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert(name.clone());
+                }
+            }
+            StmtKind::While(condition, body, increment) => {
+                let condition = self.condition(condition);
+                match increment {
+                    // `for` runs its increment after `continue` too, just like Lox.
+                    Some(increment) => {
+                        let increment = self.expr(increment);
+                        self.line(&format!("for (; {condition}; {increment}) {{"));
+                    }
+                    None => self.line(&format!("while ({condition}) {{")),
+                }
+                self.body(body);
+                self.line("}");
+            }
+        }
+    }
+
+    /// Emits the inside of a braced body, unwrapping a block so it is not braced twice.
+    fn body(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Block(statements) => self.block_body(statements),
+            _ => self.block_body(std::slice::from_ref(stmt)),
+        }
+    }
+
+    fn block_body(&mut self, statements: &[Stmt]) {
+        self.indent += 1;
+        self.scopes.push(HashSet::new());
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.scopes.pop();
+        self.indent -= 1;
+    }
+
+    /// Emits a condition as a JavaScript boolean, with Lox's idea of truthiness.
+    fn condition(&self, condition: &Expr) -> String {
+        let expr = self.expr(condition);
+        if is_boolean(condition) {
+            expr
+        } else {
+            format!("$lox.truthy({expr})")
+        }
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Assign(name, value) => {
+                format!("{} = {}", self.variable(name), self.expr(value))
+            }
+            ExprKind::Binary(_, operator, _) if operator.type_ == Comma => {
+                let operands: Vec<_> = expr
+                    .comma_operands()
+                    .into_iter()
+                    .map(|e| self.expr(e))
+                    .collect();
+                // Parenthesized so it cannot be mistaken for a list of arguments.
+                format!("({})", operands.join(", "))
+            }
+            ExprKind::Binary(left, operator, right) => {
+                let (left, right) = (self.expr(left), self.expr(right));
+                match operator.type_ {
+                    EqualEqual => format!("$lox.eq({left}, {right})"),
+                    BangEqual => format!("!$lox.eq({left}, {right})"),
+                    In => format!("$lox.contains({right}, {left})"),
+                    LessLess => format!("$lox.shl({left}, {right})"),
+                    GreaterGreater => format!("$lox.shr({left}, {right})"),
+                    _ => format!("{left} {} {right}", operator.lexeme),
+                }
+            }
+            ExprKind::Call(callee, _, arguments) => {
+                let arguments: Vec<_> = arguments.iter().map(|a| self.expr(a)).collect();
+                format!("{}({})", self.expr(callee), arguments.join(", "))
+            }
+            ExprKind::Get(object, name) => format!("{}.{}", self.expr(object), name.lexeme),
+            ExprKind::Grouping(inner) => format!("({})", self.expr(inner)),
+            ExprKind::Index(object, _, index) => {
+                format!("$lox.index({}, {})", self.expr(object), self.expr(index))
+            }
+            ExprKind::Literal(literal) => literal_js(literal),
+            ExprKind::Logical(left, operator, right) => {
+                let helper = if operator.type_ == Or { "or" } else { "and" };
+                format!(
+                    "$lox.{helper}({}, () => {})",
+                    self.expr(left),
+                    self.expr(right)
+                )
+            }
+            ExprKind::Map(_, entries) => {
+                let entries: Vec<_> = entries
+                    .iter()
+                    .map(|(key, value)| format!("[{}, {}]", self.expr(key), self.expr(value)))
+                    .collect();
+                format!("$lox.map([{}])", entries.join(", "))
+            }
+            ExprKind::SetIndex(object, _, index, value) => format!(
+                "$lox.setIndex({}, {}, {})",
+                self.expr(object),
+                self.expr(index),
+                self.expr(value)
+            ),
+            ExprKind::Unary(operator, right) if operator.type_ == Minus => {
+                let right = self.expr(right);
+                // Keep `- -x` from turning into a decrement.
+                let space = if right.starts_with('-') { " " } else { "" };
+                format!("-{space}{right}")
+            }
+            ExprKind::Unary(_, right) => match &right.kind {
+                ExprKind::Binary(..) if is_boolean(right) => format!("!({})", self.expr(right)),
+                _ if is_boolean(right) => format!("!{}", self.expr(right)),
+                _ => format!("!$lox.truthy({})", self.expr(right)),
+            },
+            ExprKind::Variable(name) => self.variable(name),
+            ExprKind::Global(name) => self.global(name, None),
+            ExprKind::SetGlobal(name, value) => self.global(name, Some(self.expr(value))),
+        }
+    }
+
+    /// Reads the global `name`, or assigns `value` to it, whatever locals shadow it.
+    fn global(&self, token: &Token, value: Option<String>) -> String {
+        let name = token.lexeme.as_str();
+        let target = if !self.scopes[0].contains(name) {
+            self.native(token)
+        } else if self.scopes[1..].iter().any(|scope| scope.contains(name)) {
+            self.shadowed_globals.borrow_mut().insert(name.to_owned());
+            let name = js_name(name);
+            return value.map_or_else(
+                || format!("$global_{name}()"),
+                |value| format!("$set_global_{name}({value})"),
+            );
+        } else {
+            js_name(name)
+        };
+        match value {
+            Some(value) => format!("{target} = {value}"),
+            None => target,
+        }
+    }
+
+    /// A variable the program declared keeps its name; anything else is a native global.
+    fn variable(&self, name: &Token) -> String {
+        if self.scopes.iter().any(|scope| scope.contains(&name.lexeme)) {
+            js_name(&name.lexeme)
+        } else {
+            self.native(name)
+        }
+    }
+
+    /// Refers to a native, reporting an error if it is one of rox's that the prelude lacks.
+    fn native(&self, name: &Token) -> String {
+        let lexeme = name.lexeme.as_str();
+        if self.rox_natives.contains(lexeme) && !JS_NATIVES.contains(&lexeme) {
+            self.diagnostics
+                .borrow_mut()
+                .push(Diagnostic::error_at_token(
+                    name,
+                    "This native is not available in JavaScript.",
+                ));
+        }
+        format!("$globals.{lexeme}")
+    }
+}
+
+/// Whether an expression always produces a boolean, so it needs no truthiness check.
+fn is_boolean(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Binary(_, operator, _) => matches!(
+            operator.type_,
+            EqualEqual | BangEqual | Greater | GreaterEqual | Less | LessEqual | In
+        ),
+        ExprKind::Unary(operator, _) => operator.type_ == Bang,
+        ExprKind::Literal(Literal::Bool(_)) => true,
+        ExprKind::Grouping(inner) => is_boolean(inner),
+        _ => false,
+    }
+}
+
+fn js_name(name: &str) -> String {
+    if RESERVED.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_owned()
+    }
+}
+
+fn literal_js(literal: &Literal) -> String {
+    match literal {
+        Literal::String_(s) => {
+            let mut quoted = String::with_capacity(s.len() + 2);
+            quoted.push('"');
+            for c in s.chars() {
+                match c {
+                    '"' => quoted.push_str("\\\""),
+                    '\\' => quoted.push_str("\\\\"),
+                    '\n' => quoted.push_str("\\n"),
+                    '\r' => quoted.push_str("\\r"),
+                    '\u{2028}' | '\u{2029}' => {
+                        let _ = write!(quoted, "\\u{:04x}", u32::from(c));
+                    }
+                    _ => quoted.push(c),
+                }
+            }
+            quoted.push('"');
+            quoted
+        }
+        Literal::Number(n) => n.to_string(),
+        Literal::BigInt(n) => format!("{n}n"),
+        Literal::Bool(b) => b.to_string(),
+        Literal::Nil => "null".to_owned(),
+    }
+}
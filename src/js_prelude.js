@@ -0,0 +1,187 @@
+"use strict";
+
+// Runtime support for Lox programs compiled by `rox emit-js`.
+const $lox = (() => {
+  class Exit {
+    constructor(code) {
+      this.code = code;
+    }
+  }
+
+  let line = "";
+  const write = (text) => {
+    const lines = (line + text).split("\n");
+    line = lines.pop();
+    lines.forEach((complete) => console.log(complete));
+  };
+
+  const isNumber = (value) => typeof value === "number";
+
+  const typeName = (value) => {
+    if (value === null) return "nil";
+    if (value instanceof Map) return "map";
+    if (typeof value === "function") return "native function";
+    if (typeof value === "object") return "namespace";
+    return { string: "string", boolean: "bool", number: "number", bigint: "bigint" }[
+      typeof value
+    ];
+  };
+
+  const str = (value, nested = false, seen = []) => {
+    if (value === null) return "nil";
+    if (typeof value === "string") return nested ? JSON.stringify(value) : value;
+    if (isNumber(value)) {
+      if (Number.isNaN(value)) return "NaN";
+      return Object.is(value, -0) ? "-0" : String(value);
+    }
+    if (typeof value === "function") return `<native fn ${value.name}>`;
+    if (value instanceof Map) {
+      if (seen.includes(value)) return "{...}";
+      seen.push(value);
+      const entries = [...value].map(
+        ([key, item]) => `${str(key, true, seen)}: ${str(item, true, seen)}`
+      );
+      seen.pop();
+      return `{${entries.join(", ")}}`;
+    }
+    if (typeof value === "object") return `<namespace ${value.name}>`;
+    return String(value);
+  };
+
+  const truthy = (value) => value !== null && value !== false;
+
+  // `comparing` holds the pairs of maps already being compared further up, which are
+  // assumed equal so that cyclic maps terminate.
+  const eq = (a, b, comparing = []) => {
+    if (a instanceof Map && b instanceof Map) {
+      if (a === b || comparing.some(([x, y]) => x === a && y === b)) return true;
+      if (a.size !== b.size) return false;
+      comparing.push([a, b]);
+      const equal = [...a].every(
+        ([key, value]) => b.has(key) && eq(value, b.get(key), comparing)
+      );
+      comparing.pop();
+      return equal;
+    }
+    return a === b;
+  };
+
+  const checkKey = (key) => {
+    if (!["string", "number", "bigint", "boolean"].includes(typeof key) && key !== null) {
+      throw new Error("Map keys must be strings, numbers, booleans or nil.");
+    }
+    return key;
+  };
+
+  const checkMap = (value) => {
+    if (!(value instanceof Map)) throw new Error("Only maps can be indexed.");
+    return value;
+  };
+
+  const frozen = new WeakSet();
+
+  const bigintShift = (amount) => {
+    if (amount < 0n || amount > 1048576n) {
+      throw new RangeError("Shift amount must be between 0n and 1048576n.");
+    }
+    return amount;
+  };
+
+  const setIndex = (map, key, value) => {
+    if (frozen.has(checkMap(map))) throw new Error("Cannot modify a frozen map.");
+    map.set(checkKey(key), value);
+    return value;
+  };
+
+  const natives = {
+    print: (...values) => {
+      write(values.map((value) => str(value)).join(" "));
+      return null;
+    },
+    println: (...values) => {
+      write(values.map((value) => str(value)).join(" ") + "\n");
+      return null;
+    },
+    type: (value) => typeName(value),
+    arity: (fn) => fn.length,
+    name: (fn) => fn.name,
+    toFixed: (n, digits) => (Number.isFinite(n) ? n.toFixed(digits) : str(n)),
+    toPrecision: (n, digits) => (Number.isFinite(n) ? n.toPrecision(digits) : str(n)),
+    toHex: (n) => n.toString(16),
+    toBinary: (n) => n.toString(2),
+    fromCharCode: (n) => {
+      if (!Number.isInteger(n) || n < 0 || n > 0x10ffff || (n >= 0xd800 && n <= 0xdfff)) {
+        throw new RangeError(`fromCharCode() expects a Unicode scalar value, got ${str(n)}.`);
+      }
+      return String.fromCodePoint(n);
+    },
+    parseInt: (text, radix = 10) => {
+      const n = Number.parseInt(text, radix);
+      return Number.isNaN(n) ? null : n;
+    },
+    parseFloat: (text) => {
+      const n = Number.parseFloat(text);
+      return Number.isNaN(n) ? null : n;
+    },
+    isNaN: (n) => Number.isNaN(n),
+    isFinite: (n) => Number.isFinite(n),
+    exit: (code = 0) => {
+      throw new Exit(code);
+    },
+    bigint: (value) => BigInt(value),
+    freeze: (map) => {
+      frozen.add(checkMap(map));
+      return map;
+    },
+    isFrozen: (map) => frozen.has(checkMap(map)),
+    has: (map, key) => checkMap(map).has(checkKey(key)),
+    delete: (map, key) => {
+      if (frozen.has(checkMap(map))) throw new Error("Cannot modify a frozen map.");
+      const value = map.get(checkKey(key));
+      map.delete(key);
+      return value === undefined ? null : value;
+    },
+    clone: (value) => (value instanceof Map ? new Map(value) : value),
+    deepCopy: (value, copies = new Map()) => {
+      if (!(value instanceof Map)) return value;
+      if (copies.has(value)) return copies.get(value);
+      const copy = new Map();
+      copies.set(value, copy);
+      value.forEach((item, key) => copy.set(key, natives.deepCopy(item, copies)));
+      return copy;
+    },
+    random: () => Math.random(),
+    randomInt: (min, max) => min + Math.floor(Math.random() * (max - min + 1)),
+    Number: { name: "Number", NAN: NaN, INFINITY: Infinity, EPSILON: Number.EPSILON },
+  };
+
+  return {
+    globals: natives,
+    print: (value) => write(str(value) + "\n"),
+    truthy,
+    eq,
+    and: (left, right) => (truthy(left) ? right() : left),
+    or: (left, right) => (truthy(left) ? left : right()),
+    map: (entries) => new Map(entries.map(([key, value]) => [checkKey(key), value])),
+    index: (map, key) => {
+      const value = checkMap(map).get(checkKey(key));
+      return value === undefined ? null : value;
+    },
+    setIndex,
+    contains: (haystack, needle) =>
+      haystack instanceof Map ? haystack.has(checkKey(needle)) : haystack.includes(needle),
+    shl: (a, b) => (typeof a === "bigint" ? a << bigintShift(b) : a * 2 ** b),
+    shr: (a, b) => (typeof a === "bigint" ? a >> bigintShift(b) : Math.floor(a / 2 ** b)),
+    run: (program) => {
+      try {
+        program();
+      } catch (error) {
+        if (!(error instanceof Exit)) throw error;
+        if (typeof process !== "undefined") process.exitCode = error.code;
+      } finally {
+        if (line !== "") console.log(line);
+      }
+    },
+  };
+})();
+const $globals = $lox.globals;
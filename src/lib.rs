@@ -0,0 +1,89 @@
+#![warn(clippy::all, clippy::pedantic, clippy::nursery)]
+#![allow(
+    clippy::module_name_repetitions,
+    clippy::must_use_candidate,
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc
+)]
+
+#[macro_use]
+extern crate anyhow;
+
+pub mod ast;
+pub mod capability;
+pub mod cfg;
+pub mod coverage;
+pub mod diagnostic;
+mod environment;
+pub mod ffi;
+pub mod interpreter;
+pub mod js;
+pub mod log;
+pub mod map;
+pub mod native;
+#[cfg(feature = "node")]
+mod node;
+pub mod parser;
+#[cfg(feature = "python")]
+mod python;
+pub mod resolver;
+pub mod scanner;
+pub mod scope;
+pub mod span;
+mod stdlib;
+pub mod tokens;
+pub mod trace;
+pub mod value;
+
+use ast::{Expr, Stmt};
+use diagnostic::Diagnostic;
+use parser::Parser;
+use resolver::Resolver;
+use scanner::Scanner;
+use tokens::Token;
+
+/// Scans, parses and resolves `source` without running it. This never panics, whatever
+/// bytes it is given, so it is safe to fuzz and to call on untrusted input.
+pub fn parse(source: &str) -> Result<Vec<Stmt>, Vec<Diagnostic>> {
+    let mut scanner = Scanner::new(source.to_owned());
+    let tokens = scanner.scan_tokens();
+    let mut diagnostics = scanner.take_diagnostics();
+    // Parsing tokens around lexical errors mostly produces confusing follow-on errors.
+    if diagnostics.is_empty() {
+        let (statements, more) = parse_tokens(tokens);
+        diagnostics.extend(more);
+        if !diagnostics.iter().any(Diagnostic::is_error) {
+            return Ok(statements);
+        }
+    }
+    Err(diagnostics)
+}
+
+/// Scans and parses `source` as a single expression, as the REPL's `:type` and `:ast`
+/// commands take. Like `parse`, this never panics.
+pub fn parse_expression(source: &str) -> Result<Expr, Vec<Diagnostic>> {
+    let mut scanner = Scanner::new(source.to_owned());
+    let tokens = scanner.scan_tokens();
+    let diagnostics = scanner.take_diagnostics();
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+    let mut parser = Parser::new(tokens);
+    parser
+        .parse_expression()
+        .ok_or_else(|| parser.take_diagnostics())
+}
+
+/// Parses and resolves already scanned tokens, returning the program along with every error
+/// and warning found. The resolver only runs if parsing succeeded.
+pub fn parse_tokens(tokens: Vec<Token>) -> (Vec<Stmt>, Vec<Diagnostic>) {
+    let mut parser = Parser::new(tokens);
+    let statements = parser.parse();
+    let mut diagnostics = parser.take_diagnostics();
+    if !diagnostics.iter().any(Diagnostic::is_error) {
+        let mut resolver = Resolver::new();
+        resolver.resolve(&statements);
+        diagnostics.extend(resolver.take_diagnostics());
+    }
+    (statements, diagnostics)
+}
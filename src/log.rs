@@ -0,0 +1,85 @@
+//! The levels `Log` messages are written at, and how they are written.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How severe a log message is. An interpreter writes messages at its level and above.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Level {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        };
+        f.pad(name)
+    }
+}
+
+impl FromStr for Level {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "debug" => Ok(Self::Debug),
+            "info" => Ok(Self::Info),
+            "warn" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            _ => Err(anyhow!(
+                "expects one of debug, info, warn or error, got '{name}'"
+            )),
+        }
+    }
+}
+
+/// Writes `message` to standard error, led by the time in UTC and its level, such as
+/// `2024-05-01T09:30:00.250Z WARN  disk almost full`.
+pub(crate) fn write(level: Level, message: &str) {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let level = level.to_string().to_uppercase();
+    // A log line that cannot be written has nowhere better to go.
+    let _ = writeln!(
+        io::stderr().lock(),
+        "{} {level:<5} {message}",
+        timestamp(since_epoch.as_secs(), since_epoch.subsec_millis())
+    );
+}
+
+/// Formats a Unix time as an ISO 8601 date and time in UTC.
+fn timestamp(seconds: u64, millis: u32) -> String {
+    let (days, second_of_day) = (seconds / 86_400, seconds % 86_400);
+    // Howard Hinnant's civil_from_days, counting 400-year eras from 0000-03-01.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{millis:03}Z",
+        second_of_day / 3600,
+        second_of_day / 60 % 60,
+        second_of_day % 60
+    )
+}
@@ -1,104 +1,322 @@
-#![feature(result_cloned, const_option)]
 #![warn(clippy::all, clippy::pedantic, clippy::nursery)]
 #![allow(clippy::module_name_repetitions)]
 
 #[macro_use]
 extern crate anyhow;
 
-mod ast;
-mod environment;
-mod interpreter;
-mod parser;
-mod scanner;
-mod tokens;
-mod value;
+mod bench;
+mod cli;
+mod cover;
+mod graph;
+mod highlight;
 
 use anyhow::Result;
-use interpreter::Interpreter;
-use parser::Parser;
-use scanner::Scanner;
+use cli::{Command, Options};
+use rox::ast::{Expr, ExprVisitor, Stmt};
+use rox::diagnostic::Diagnostic;
+use rox::interpreter::{Exit, Interpreter};
+use rox::parser::Parser;
+use rox::resolver::Resolver;
+use rox::scanner::Scanner;
+use rox::tokens::Token;
+use rox::trace::Recording;
 use std::env;
+use std::fmt;
 use std::fs;
 use std::io;
-use std::io::Write;
-use std::num::NonZeroUsize;
+use std::io::{IsTerminal, Read, Write};
 use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
-use tokens::{Token, TokenType};
+use std::process;
+use std::time::Instant;
 
-static HAD_ERROR: AtomicBool = AtomicBool::new(false);
+/// Returned by `run` when the source did not compile. The diagnostics have already been
+/// printed by then.
+#[derive(Debug)]
+struct CompileError;
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the source did not compile")
+    }
+}
+
+impl std::error::Error for CompileError {}
 
 fn main() -> Result<()> {
-    let mut args = env::args();
-    args.next(); // Consume `rox`
-    let interpreter = Interpreter::new();
-    if let Some(filename) = args.next() {
-        run_file(filename, interpreter)?;
-    } else {
-        run_prompt(&interpreter)?;
+    let options = match cli::parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {e}");
+            eprintln!("Run 'rox --help' for usage.");
+            process::exit(64);
+        }
+    };
+    let mut interpreter = Interpreter::new();
+    for &capability in &options.capabilities {
+        interpreter.allow(capability);
+    }
+    if let Some(seed) = options.seed {
+        interpreter.seed_random(seed);
+    }
+    if let Some(level) = options.log_level {
+        interpreter.set_log_level(level);
+    }
+    for plugin in &options.plugins {
+        if let Err(error) = rox::ffi::load_plugin(&mut interpreter, plugin) {
+            eprintln!("error: {error}");
+            process::exit(70);
+        }
+    }
+    match &options.command {
+        Command::Help => println!("{}", cli::USAGE),
+        Command::Version => println!("rox {}", env!("CARGO_PKG_VERSION")),
+        Command::Run(filename) => run_file(filename, interpreter, &options),
+        Command::Repl => run_prompt(interpreter, &options)?,
+        Command::Bench(filename) => bench::run(filename, &options)?,
+        Command::Graph(filename) => graph::run(filename, &options)?,
+        Command::Cfg(filename) => graph::cfg(filename, &options)?,
+        Command::EmitJs(filename) => {
+            let (_, statements) = parse_script(filename, &options)?;
+            match rox::js::emit(&statements) {
+                Ok(js) => print!("{js}"),
+                Err(diagnostics) => {
+                    let _ = report_diagnostics(&diagnostics);
+                    process::exit(65);
+                }
+            }
+        }
+        Command::Cover(filename) => cover::run(filename, interpreter, &options)?,
     }
     Ok(())
 }
 
-fn run_file<P: AsRef<Path>>(filename: P, interpreter: Interpreter) -> Result<()> {
-    let contents = fs::read_to_string(filename)?;
-    run(&contents, interpreter);
-    if had_error() {
-        panic!("There was an error running the file!")
+fn run_file<P: AsRef<Path>>(filename: P, mut interpreter: Interpreter, options: &Options) {
+    let contents = match read_source(filename.as_ref(), options) {
+        Ok(contents) => contents,
+        Err(error) => {
+            eprintln!("error: {error}");
+            process::exit(66);
+        }
+    };
+    let recording = match start_trace(&mut interpreter, options) {
+        Ok(recording) => recording,
+        Err(error) => {
+            eprintln!("error: {error}");
+            process::exit(66);
+        }
+    };
+    let result = run(&contents, &mut interpreter, options);
+    // Save the trace before a runtime error or `exit()` ends the process.
+    if let (Some(recording), Some(path)) = (&recording, &options.record) {
+        if let Err(error) = recording.save(path) {
+            eprintln!("error: {error}");
+            process::exit(74);
+        }
+    }
+    if let Err(error) = result {
+        if error.is::<CompileError>() {
+            process::exit(65);
+        }
+        report_runtime_error(&error);
+        process::exit(70);
+    }
+}
+
+/// Replays the trace given with `--replay`, and starts recording if `--record` was given.
+fn start_trace(interpreter: &mut Interpreter, options: &Options) -> Result<Option<Recording>> {
+    if let Some(path) = &options.replay {
+        rox::trace::replay(interpreter, path)?;
+    }
+    options
+        .record
+        .as_ref()
+        .map(|_| Recording::start(interpreter))
+        .transpose()
+}
+
+/// Reads and parses a script, exiting as `rox run` would if it does not compile.
+fn parse_script(filename: &Path, options: &Options) -> Result<(String, Vec<Stmt>)> {
+    let contents = read_source(filename, options)?;
+    match rox::parse(&contents) {
+        Ok(statements) => Ok((contents, statements)),
+        Err(diagnostics) => {
+            let _ = report_diagnostics(&diagnostics);
+            process::exit(65);
+        }
     }
-    Ok(())
 }
 
-fn run_prompt(interpreter: &Interpreter) -> Result<()> {
+/// Reads a script, refusing files over `--max-source-size` and, unless `--lossy-utf8` was
+/// given, files that are not valid UTF-8.
+fn read_source(path: &Path, options: &Options) -> Result<String> {
+    let file =
+        fs::File::open(path).map_err(|e| anyhow!("cannot open {}: {}", path.display(), e))?;
+    // Read one byte past the limit so an oversized file is caught even when its length is
+    // not known up front, as with pipes.
+    let mut bytes = Vec::new();
+    file.take(options.max_source_size as u64 + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| anyhow!("cannot read {}: {}", path.display(), e))?;
+    if bytes.len() > options.max_source_size {
+        return Err(anyhow!(
+            "{} is larger than the {} byte limit; raise it with --max-source-size",
+            path.display(),
+            options.max_source_size
+        ));
+    }
+    match String::from_utf8(bytes) {
+        Ok(source) => Ok(source),
+        Err(e) if options.lossy_utf8 => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+        Err(e) => Err(anyhow!(
+            "{} is not valid UTF-8: bad data at byte offset {}; pass --lossy-utf8 to replace it",
+            path.display(),
+            e.utf8_error().valid_up_to()
+        )),
+    }
+}
+
+/// Runs each line entered against the same interpreter, so definitions carry over. Lines
+/// starting with `:type` or `:ast` inspect an expression instead.
+fn run_prompt(mut interpreter: Interpreter, options: &Options) -> Result<()> {
     let mut stdout = io::stdout();
     let stdin = io::stdin();
+    let color = options.color
+        && env::var_os("NO_COLOR").is_none()
+        && stdin.is_terminal()
+        && stdout.is_terminal();
     let mut input = String::new();
     loop {
         write!(stdout, "> ")?;
         stdout.flush()?;
-        stdin.read_line(&mut input)?;
-        run(&input, interpreter.clone());
-        set_had_error(false);
+        if stdin.read_line(&mut input)? == 0 {
+            writeln!(stdout)?;
+            return Ok(());
+        }
+        if color {
+            // Go back up to the line the terminal echoed and redraw it highlighted.
+            let line = input.trim_end_matches(['\r', '\n']);
+            writeln!(stdout, "\x1b[1A\r> {}\x1b[K", highlight::highlight(line))?;
+        }
+        let result = if let Some(source) = input.trim_start().strip_prefix(":type") {
+            show_type(source, &mut interpreter)
+        } else if let Some(source) = input.trim_start().strip_prefix(":ast") {
+            show_ast(source)
+        } else {
+            run(&input, &mut interpreter, options)
+        };
+        if let Err(error) = result {
+            if !error.is::<CompileError>() {
+                report_runtime_error(&error);
+            }
+        }
         input.clear();
     }
 }
 
-fn run(source: &str, mut interpreter: Interpreter) {
-    let mut scanner = Scanner::new(source.to_owned());
-    let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(tokens);
-    let statements = parser.parse();
-    if had_error() {
-        return;
-    }
-    interpreter.interpret(&statements);
+fn parse_expression(source: &str) -> Result<Expr> {
+    rox::parse_expression(source).or_else(|diagnostics| {
+        report_diagnostics(&diagnostics)?;
+        Err(CompileError.into())
+    })
 }
 
-pub fn error(line: NonZeroUsize, message: &str) {
-    report(line, "", message);
+/// Evaluates an expression and prints the type of its value, but not the value itself.
+fn show_type(source: &str, interpreter: &mut Interpreter) -> Result<()> {
+    let expr = parse_expression(source)?;
+    println!("{}", interpreter.evaluate(expr)?.type_name());
+    Ok(())
 }
 
-pub fn error_at_token(token: &Token, message: &str) {
-    if token.type_ == TokenType::Eof {
-        report(token.line, " at end", message);
+/// Prints the syntax tree of an expression without evaluating it.
+fn show_ast(source: &str) -> Result<()> {
+    let expr = parse_expression(source)?;
+    println!("{expr}");
+    Ok(())
+}
+
+fn run(source: &str, interpreter: &mut Interpreter, options: &Options) -> Result<()> {
+    let mut scanner = if options.dump_tokens {
+        Scanner::with_trivia(source.to_owned())
     } else {
-        report(
-            token.line,
-            &format!(" at '{}'", token.lexeme),
-            message,
+        Scanner::new(source.to_owned())
+    };
+    let start = Instant::now();
+    let tokens = scanner.scan_tokens();
+    let scan_time = start.elapsed();
+    let token_count = tokens.len();
+    if options.dump_tokens {
+        dump_tokens(&tokens);
+    }
+    // Only parse if scanning succeeded, and only resolve if parsing did, as `rox::parse`
+    // does. The phases are run one by one here so `--time` can report on each.
+    report_diagnostics(&scanner.take_diagnostics())?;
+    let start = Instant::now();
+    let mut parser = Parser::new(tokens);
+    let statements = parser.parse();
+    let parse_time = start.elapsed();
+    report_diagnostics(&parser.take_diagnostics())?;
+    let start = Instant::now();
+    let mut resolver = Resolver::new();
+    resolver.resolve(&statements);
+    let resolve_time = start.elapsed();
+    report_diagnostics(&resolver.take_diagnostics())?;
+    if options.dump_ast {
+        println!("{statements:#?}");
+    }
+    let executed_before = interpreter.statements_executed();
+    let start = Instant::now();
+    let result = interpreter.interpret(&statements);
+    let interpret_time = start.elapsed();
+    if options.time {
+        eprintln!("Timing:");
+        eprintln!("  scanning      {scan_time:>10.3?}  {token_count} tokens");
+        eprintln!(
+            "  parsing       {parse_time:>10.3?}  {} top-level statements",
+            statements.len()
+        );
+        eprintln!("  resolving     {resolve_time:>10.3?}");
+        eprintln!(
+            "  interpreting  {interpret_time:>10.3?}  {} statements executed",
+            interpreter.statements_executed() - executed_before
         );
     }
+    result
 }
 
-fn report(line: NonZeroUsize, where_: &str, message: &str) {
-    println!("[line {}] Error{}: {}", line, where_, message);
-    set_had_error(true);
+/// Prints every diagnostic, failing with `CompileError` after a count of the errors if any
+/// of them is one.
+fn report_diagnostics(diagnostics: &[Diagnostic]) -> Result<()> {
+    for diagnostic in diagnostics {
+        eprintln!("{diagnostic}");
+    }
+    match diagnostics.iter().filter(|d| d.is_error()).count() {
+        0 => Ok(()),
+        1 => Err(CompileError.into()),
+        errors => {
+            eprintln!("{errors} errors found.");
+            Err(CompileError.into())
+        }
+    }
 }
 
-fn had_error() -> bool {
-    HAD_ERROR.load(Ordering::Relaxed)
+/// Prints a runtime error, or ends the process if the script asked to exit.
+fn report_runtime_error(error: &anyhow::Error) {
+    if let Some(Exit(code)) = error.downcast_ref::<Exit>() {
+        // `process::exit` does not flush stdout, which may hold output from `print()`.
+        let _ = io::stdout().flush();
+        process::exit(*code);
+    }
+    eprintln!("{error}");
 }
 
-fn set_had_error(b: bool) {
-    HAD_ERROR.store(b, Ordering::Relaxed)
+fn dump_tokens(tokens: &[Token]) {
+    for token in tokens {
+        for trivia in &token.leading_trivia {
+            println!("[line {}] {}", token.line, trivia.text);
+        }
+        println!("[line {}] {}", token.line, token);
+        for trivia in &token.trailing_trivia {
+            println!("[line {}]     {}", token.line, trivia.text);
+        }
+    }
 }
@@ -0,0 +1,108 @@
+use crate::value::Value;
+use anyhow::Result;
+use num_bigint::BigInt;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// The hashable form of a map key. Only strings, numeric values, booleans and nil may be keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum MapKey {
+    String_(String),
+    Number(u64),
+    BigInt(BigInt),
+    Decimal(Decimal),
+    Bool(bool),
+    Nil,
+}
+
+impl MapKey {
+    fn new(value: &Value) -> Result<Self> {
+        match value {
+            Value::String_(s) => Ok(Self::String_(s.clone())),
+            // -0 and 0 are equal as numbers, so they must be the same key.
+            Value::Number(n) if *n == 0.0 => Ok(Self::Number(0.0_f64.to_bits())),
+            // NaN equals nothing, not even itself, so a NaN key could never be found again.
+            Value::Number(n) if n.is_nan() => Err(anyhow!("Map keys cannot be NaN.")),
+            Value::Number(n) => Ok(Self::Number(n.to_bits())),
+            Value::BigInt(n) => Ok(Self::BigInt(n.clone())),
+            // Decimals hash by value, so 1.0 and 1.00 are the same key.
+            Value::Decimal(n) => Ok(Self::Decimal(*n)),
+            Value::Bool(b) => Ok(Self::Bool(*b)),
+            Value::Nil => Ok(Self::Nil),
+            _ => Err(anyhow!(
+                "Map keys must be strings, numbers, booleans or nil."
+            )),
+        }
+    }
+}
+
+/// A map that remembers the order its keys were first inserted in.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Map {
+    indices: HashMap<MapKey, usize>,
+    entries: Vec<(Value, Value)>,
+    frozen: bool,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &Value) -> Result<Option<&Value>> {
+        let key = MapKey::new(key)?;
+        Ok(self.indices.get(&key).map(|&i| &self.entries[i].1))
+    }
+
+    /// Sets `key` to `value`, failing if the map has been frozen.
+    pub fn insert(&mut self, key: Value, value: Value) -> Result<()> {
+        if self.frozen {
+            return Err(anyhow!("Cannot modify a frozen map."));
+        }
+        let hashed = MapKey::new(&key)?;
+        if let Some(&i) = self.indices.get(&hashed) {
+            self.entries[i].1 = value;
+        } else {
+            self.indices.insert(hashed, self.entries.len());
+            self.entries.push((key, value));
+        }
+        Ok(())
+    }
+
+    /// Removes `key`, returning its value if it was there. Later keys keep their order.
+    pub fn remove(&mut self, key: &Value) -> Result<Option<Value>> {
+        if self.frozen {
+            return Err(anyhow!("Cannot modify a frozen map."));
+        }
+        let Some(i) = self.indices.remove(&MapKey::new(key)?) else {
+            return Ok(None);
+        };
+        for index in self.indices.values_mut() {
+            if *index > i {
+                *index -= 1;
+            }
+        }
+        Ok(Some(self.entries.remove(i).1))
+    }
+
+    /// Makes every later `insert` fail. There is no way to thaw a map.
+    pub const fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    pub const fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Value, &Value)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
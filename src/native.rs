@@ -0,0 +1,623 @@
+// Every native shares the `NativeFn` signature, and Lox numbers are f64s that natives convert
+// to and from integers once they have checked the range.
+#![allow(
+    clippy::unnecessary_wraps,
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss
+)]
+
+use crate::capability::Capability;
+use crate::environment::Environment;
+use crate::interpreter::{Exit, Interpreter};
+use crate::map::Map;
+use crate::value::Value;
+use anyhow::Result;
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
+use rust_decimal::Decimal;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+
+pub type NativeFn = fn(&mut Interpreter, &[Value]) -> Result<Value>;
+type NativeClosure = dyn Fn(&mut Interpreter, &[Value]) -> Result<Value>;
+
+/// A function implemented in Rust and callable from Lox.
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    /// The exact number of arguments expected, or `None` if any number is accepted.
+    pub arity: Option<usize>,
+    function: Callable,
+}
+
+#[derive(Clone)]
+enum Callable {
+    Fn(NativeFn),
+    /// A closure, for hosts whose natives carry state, such as the C API's callbacks.
+    Closure(Rc<NativeClosure>),
+}
+
+impl NativeFunction {
+    pub const fn new(name: &'static str, arity: Option<usize>, function: NativeFn) -> Self {
+        Self {
+            name,
+            arity,
+            function: Callable::Fn(function),
+        }
+    }
+
+    pub fn with_closure<F>(name: &'static str, arity: Option<usize>, function: F) -> Self
+    where
+        F: Fn(&mut Interpreter, &[Value]) -> Result<Value> + 'static,
+    {
+        Self {
+            name,
+            arity,
+            function: Callable::Closure(Rc::new(function)),
+        }
+    }
+
+    /// Calls the function. The caller has already checked the number of arguments.
+    pub fn call(&self, interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+        match &self.function {
+            Callable::Fn(function) => function(interpreter, arguments),
+            Callable::Closure(function) => function(interpreter, arguments),
+        }
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+/// A named group of natives reached with `Name.member`, such as `Process.run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Namespace {
+    pub name: &'static str,
+    members: HashMap<&'static str, Value>,
+}
+
+impl Namespace {
+    pub fn new(name: &'static str, functions: Vec<NativeFunction>) -> Self {
+        let members = functions
+            .into_iter()
+            .map(|function| (function.name, Value::NativeFunction(function)))
+            .collect();
+        Self { name, members }
+    }
+
+    /// Adds a member that is a plain value rather than a function.
+    #[must_use]
+    pub fn constant(mut self, name: &'static str, value: Value) -> Self {
+        self.members.insert(name, value);
+        self
+    }
+
+    pub fn get(&self, member: &str) -> Option<Value> {
+        self.members.get(member).cloned()
+    }
+
+    /// Returns a copy of the namespace with `name` set to `value`.
+    pub(crate) fn with_member(&self, name: &'static str, value: Value) -> Self {
+        let mut members = self.members.clone();
+        members.insert(name, value);
+        Self {
+            name: self.name,
+            members,
+        }
+    }
+}
+
+/// A host resource, such as a socket, that natives hand to scripts as an opaque value.
+pub struct Handle {
+    pub kind: &'static str,
+    resource: RefCell<Option<Box<dyn Any>>>,
+}
+
+impl Handle {
+    pub fn new<T: Any>(kind: &'static str, resource: T) -> Self {
+        Self {
+            kind,
+            resource: RefCell::new(Some(Box::new(resource))),
+        }
+    }
+
+    /// Runs `f` on the resource, failing if the handle has been closed or holds a different
+    /// kind of resource.
+    pub fn with<T: Any, R>(
+        &self,
+        function: &str,
+        f: impl FnOnce(&mut T) -> Result<R>,
+    ) -> Result<R> {
+        let mut resource = self.resource.borrow_mut();
+        match resource
+            .as_mut()
+            .map(|resource| resource.downcast_mut::<T>())
+        {
+            Some(Some(resource)) => f(resource),
+            Some(None) => Err(anyhow!("{function}() cannot use a {}.", self.kind)),
+            None => Err(anyhow!("{function}() was given a closed {}.", self.kind)),
+        }
+    }
+
+    /// Drops the resource, returning whether it was still open.
+    pub fn close(&self) -> bool {
+        self.resource.borrow_mut().take().is_some()
+    }
+}
+
+impl PartialEq for Handle {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle({})", self.kind)
+    }
+}
+
+/// The natives every interpreter starts with.
+pub fn globals() -> Vec<NativeFunction> {
+    vec![
+        NativeFunction::new("print", None, print),
+        NativeFunction::new("println", None, println),
+        NativeFunction::new("type", Some(1), type_),
+        NativeFunction::new("arity", Some(1), arity),
+        NativeFunction::new("name", Some(1), name),
+        NativeFunction::new("toFixed", Some(2), to_fixed),
+        NativeFunction::new("toPrecision", Some(2), to_precision),
+        NativeFunction::new("toHex", Some(1), to_hex),
+        NativeFunction::new("toBinary", Some(1), to_binary),
+        NativeFunction::new("fromCharCode", Some(1), from_char_code),
+        NativeFunction::new("parseInt", None, parse_int),
+        NativeFunction::new("parseFloat", Some(1), parse_float),
+        NativeFunction::new("isNaN", Some(1), is_nan),
+        NativeFunction::new("isFinite", Some(1), is_finite),
+        NativeFunction::new("exit", None, exit),
+        NativeFunction::new("bigint", Some(1), bigint),
+        NativeFunction::new("decimal", Some(1), decimal),
+        NativeFunction::new("freeze", Some(1), freeze),
+        NativeFunction::new("isFrozen", Some(1), is_frozen),
+        NativeFunction::new("has", Some(2), has),
+        NativeFunction::new("delete", Some(2), delete),
+        NativeFunction::new("clone", Some(1), clone),
+        NativeFunction::new("deepCopy", Some(1), deep_copy),
+        NativeFunction::new("random", Some(0), random),
+        NativeFunction::new("randomInt", Some(2), random_int),
+        NativeFunction::new("globals", Some(0), globals_map),
+        NativeFunction::new("locals", Some(0), locals_map),
+        NativeFunction::new("loadPlugin", Some(1), load_plugin),
+    ]
+}
+
+fn print(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    write_values(interpreter, arguments)?;
+    Ok(Value::Nil)
+}
+
+fn println(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    write_values(interpreter, arguments)?;
+    writeln!(interpreter.output())?;
+    Ok(Value::Nil)
+}
+
+fn write_values(interpreter: &Interpreter, values: &[Value]) -> Result<()> {
+    let mut output = interpreter.output();
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            write!(output, " ")?;
+        }
+        write!(output, "{value}")?;
+    }
+    Ok(())
+}
+
+fn type_(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    Ok(Value::String_(arguments[0].type_name().to_owned()))
+}
+
+/// Returns the number of parameters a function takes, or nil if it accepts any number.
+fn arity(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    match &arguments[0] {
+        Value::NativeFunction(function) => Ok(function
+            .arity
+            .map_or(Value::Nil, |arity| Value::Number(arity as f64))),
+        other => Err(anyhow!(
+            "arity() expects a function, got {}.",
+            other.type_name()
+        )),
+    }
+}
+
+fn name(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    match &arguments[0] {
+        Value::NativeFunction(function) => Ok(Value::String_(function.name.to_owned())),
+        other => Err(anyhow!(
+            "name() expects a function, got {}.",
+            other.type_name()
+        )),
+    }
+}
+
+/// Formats a number with exactly `digits` digits after the decimal point.
+fn to_fixed(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let n = number_argument("toFixed", &arguments[0])?;
+    let digits = digits_argument("toFixed", &arguments[1], 0)?;
+    if !n.is_finite() {
+        return Ok(Value::String_(Value::Number(n).to_string()));
+    }
+    Ok(Value::String_(format!("{n:.digits$}")))
+}
+
+/// Formats a number with `digits` significant digits, switching to exponent notation for
+/// very large or very small magnitudes.
+fn to_precision(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let n = number_argument("toPrecision", &arguments[0])?;
+    let digits = digits_argument("toPrecision", &arguments[1], 1)?;
+    if !n.is_finite() {
+        return Ok(Value::String_(Value::Number(n).to_string()));
+    }
+    let scientific = format!("{:.*e}", digits - 1, n);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i64 = exponent.parse().unwrap();
+    let formatted = if exponent < -6 || exponent >= digits as i64 {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{mantissa}e{sign}{}", exponent.abs())
+    } else {
+        let decimals = (digits as i64 - 1 - exponent).max(0) as usize;
+        format!("{n:.decimals$}")
+    };
+    Ok(Value::String_(formatted))
+}
+
+fn to_hex(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let n = integer_argument("toHex", &arguments[0])?;
+    let sign = if n < 0 { "-" } else { "" };
+    Ok(Value::String_(format!("{sign}{:x}", n.unsigned_abs())))
+}
+
+fn to_binary(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let n = integer_argument("toBinary", &arguments[0])?;
+    let sign = if n < 0 { "-" } else { "" };
+    Ok(Value::String_(format!("{sign}{:b}", n.unsigned_abs())))
+}
+
+/// Returns the one-character string for a Unicode code point. Surrogates are not
+/// characters on their own, so they are refused along with anything past U+10FFFF.
+fn from_char_code(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let n = integer_argument("fromCharCode", &arguments[0])?;
+    u32::try_from(n)
+        .ok()
+        .and_then(char::from_u32)
+        .map(|c| Value::String_(c.to_string()))
+        .ok_or_else(|| anyhow!("fromCharCode() expects a Unicode scalar value, got {n}."))
+}
+
+/// Parses an integer in the given radix (10 by default), returning nil if the whole string
+/// is not a valid integer.
+fn parse_int(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let (text, radix) = match arguments {
+        [text] => (text, 10),
+        [text, radix] => (text, integer_argument("parseInt", radix)?),
+        _ => {
+            return Err(anyhow!(
+                "parseInt() expects 1 or 2 arguments but got {}.",
+                arguments.len()
+            ))
+        }
+    };
+    let text = string_argument("parseInt", text)?;
+    if !(2..=36).contains(&radix) {
+        return Err(anyhow!("parseInt() radix must be between 2 and 36."));
+    }
+    let text = text.trim();
+    let negative = text.starts_with('-');
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    if digits.is_empty() {
+        return Ok(Value::Nil);
+    }
+    let mut n = 0.0;
+    for c in digits.chars() {
+        match c.to_digit(radix as u32) {
+            Some(digit) => n = n * radix as f64 + f64::from(digit),
+            None => return Ok(Value::Nil),
+        }
+    }
+    Ok(Value::Number(if negative { -n } else { n }))
+}
+
+/// Parses a decimal number such as `-1.5e3`, returning nil if the whole string is not one.
+fn parse_float(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let text = string_argument("parseFloat", &arguments[0])?.trim();
+    Ok(parse_decimal(text).map_or(Value::Nil, Value::Number))
+}
+
+/// Parses `text` if all of it is a decimal number such as `-1.5e3`.
+pub(crate) fn parse_decimal(text: &str) -> Option<f64> {
+    // Rust also accepts "inf" and "NaN", which are not Lox number syntax.
+    if !text
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E'))
+    {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn is_nan(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    Ok(Value::Bool(
+        number_argument("isNaN", &arguments[0])?.is_nan(),
+    ))
+}
+
+fn is_finite(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    Ok(Value::Bool(
+        number_argument("isFinite", &arguments[0])?.is_finite(),
+    ))
+}
+
+/// Stops the script with the given exit code (0 if omitted). The request unwinds through
+/// the interpreter as an `Exit` error rather than ending the process on the spot.
+fn exit(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let code = match arguments {
+        [] => 0,
+        [code] => integer_argument("exit", code)?,
+        _ => {
+            return Err(anyhow!(
+                "exit() expects 0 or 1 arguments but got {}.",
+                arguments.len()
+            ))
+        }
+    };
+    let code = i32::try_from(code).map_err(|_| anyhow!("exit() code {code} is out of range."))?;
+    Err(Exit(code).into())
+}
+
+/// Converts an integral number or a decimal string such as `"-123"` to a bigint.
+fn bigint(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    match &arguments[0] {
+        Value::BigInt(n) => Ok(Value::BigInt(n.clone())),
+        Value::Number(n) if n.is_finite() && n.fract() == 0.0 => {
+            Ok(Value::BigInt(BigInt::from_f64(*n).unwrap()))
+        }
+        Value::String_(s) => BigInt::from_str(s.trim())
+            .map(Value::BigInt)
+            .map_err(|_| anyhow!("bigint() cannot parse {s:?}.")),
+        number @ Value::Number(_) => Err(anyhow!("bigint() expects an integer, got {number}.")),
+        other => Err(anyhow!(
+            "bigint() expects an integer or a string, got {}.",
+            other.type_name()
+        )),
+    }
+}
+
+/// Converts a number or a string such as `"0.1"` to an exact decimal. Numbers convert from
+/// their printed form, so `decimal(0.1)` is exactly 0.1 rather than the nearest double.
+fn decimal(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let text = match &arguments[0] {
+        Value::Decimal(n) => return Ok(Value::Decimal(*n)),
+        Value::Number(n) if n.is_finite() => n.to_string(),
+        Value::String_(s) => s.trim().to_owned(),
+        number @ Value::Number(_) => return Err(anyhow!("decimal() cannot represent {number}.")),
+        other => {
+            return Err(anyhow!(
+                "decimal() expects a number or a string, got {}.",
+                other.type_name()
+            ))
+        }
+    };
+    Decimal::from_str_exact(&text)
+        .map(Value::Decimal)
+        .map_err(|_| anyhow!("decimal() cannot represent {text:?}."))
+}
+
+/// Makes a map read-only and returns it, so `var CONFIG = freeze({...});` works.
+fn freeze(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    map_argument("freeze", &arguments[0])?.borrow_mut().freeze();
+    Ok(arguments[0].clone())
+}
+
+fn is_frozen(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    Ok(Value::Bool(
+        map_argument("isFrozen", &arguments[0])?
+            .borrow()
+            .is_frozen(),
+    ))
+}
+
+/// Whether a map has `key`, even if its value is nil, as `key in map` does.
+fn has(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let map = map_argument("has", &arguments[0])?.borrow();
+    Ok(Value::Bool(map.get(&arguments[1])?.is_some()))
+}
+
+/// Removes a key from a map, returning its value, or nil if it was not there.
+fn delete(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let mut map = map_argument("delete", &arguments[0])?.borrow_mut();
+    Ok(map.remove(&arguments[1])?.unwrap_or(Value::Nil))
+}
+
+/// Copies a map's entries into a new, unfrozen map. Other values are returned as they are,
+/// since they cannot be mutated.
+fn clone(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    match &arguments[0] {
+        Value::Map(map) => {
+            let mut copy = Map::new();
+            for (key, value) in map.borrow().iter() {
+                copy.insert(key.clone(), value.clone())?;
+            }
+            Ok(Value::map(copy))
+        }
+        other => Ok(other.clone()),
+    }
+}
+
+/// Copies a map and every map reachable from it. A map reached twice is copied once, so
+/// shared and cyclic structure is preserved in the copy.
+fn deep_copy(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    copy_value(&arguments[0], &mut HashMap::new())
+}
+
+fn copy_value(
+    value: &Value,
+    copies: &mut HashMap<*const RefCell<Map>, Rc<RefCell<Map>>>,
+) -> Result<Value> {
+    let Value::Map(map) = value else {
+        return Ok(value.clone());
+    };
+    if let Some(copy) = copies.get(&Rc::as_ptr(map)) {
+        return Ok(Value::Map(Rc::clone(copy)));
+    }
+    let copy = Rc::new(RefCell::new(Map::new()));
+    copies.insert(Rc::as_ptr(map), Rc::clone(&copy));
+    for (key, value) in map.borrow().iter() {
+        let value = copy_value(value, copies)?;
+        copy.borrow_mut().insert(key.clone(), value)?;
+    }
+    Ok(Value::Map(copy))
+}
+
+pub(crate) fn map_argument<'a>(function: &str, value: &'a Value) -> Result<&'a RefCell<Map>> {
+    match value {
+        Value::Map(map) => Ok(map),
+        other => Err(anyhow!(
+            "{}() expects a map, got {}.",
+            function,
+            other.type_name()
+        )),
+    }
+}
+
+pub(crate) fn handle_argument<'a>(function: &str, value: &'a Value) -> Result<&'a Handle> {
+    match value {
+        Value::Handle(handle) => Ok(handle),
+        other => Err(anyhow!(
+            "{}() expects a handle, got {}.",
+            function,
+            other.type_name()
+        )),
+    }
+}
+
+pub(crate) fn string_argument<'a>(function: &str, value: &'a Value) -> Result<&'a str> {
+    match value {
+        Value::String_(s) => Ok(s),
+        other => Err(anyhow!(
+            "{}() expects a string, got {}.",
+            function,
+            other.type_name()
+        )),
+    }
+}
+
+pub(crate) fn number_argument(function: &str, value: &Value) -> Result<f64> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        other => Err(anyhow!(
+            "{}() expects a number, got {}.",
+            function,
+            other.type_name()
+        )),
+    }
+}
+
+pub(crate) fn integer_argument(function: &str, value: &Value) -> Result<i64> {
+    let n = number_argument(function, value)?;
+    if n.fract() == 0.0 && n.abs() < 2_f64.powi(63) {
+        Ok(n as i64)
+    } else {
+        Err(anyhow!("{function}() expects an integer, got {value}."))
+    }
+}
+
+/// Reads a digit count in `min..=100`.
+fn digits_argument(function: &str, value: &Value, min: i64) -> Result<usize> {
+    let digits = integer_argument(function, value)?;
+    if (min..=100).contains(&digits) {
+        Ok(digits as usize)
+    } else {
+        Err(anyhow!(
+            "{function}() digits must be between {min} and 100."
+        ))
+    }
+}
+
+/// Returns a number in `[0, 1)`.
+fn random(interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value> {
+    Ok(Value::Number(interpreter.random().rand_float()))
+}
+
+/// Returns an integer from `min` to `max`, both included.
+fn random_int(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let min = integer_argument("randomInt", &arguments[0])?;
+    let max = integer_argument("randomInt", &arguments[1])?;
+    if min > max {
+        return Err(anyhow!(
+            "randomInt() expects min <= max, got {min} and {max}."
+        ));
+    }
+    // Integer arguments stay within ±2^63, so the count of choices fits in a u64.
+    let choices = (i128::from(max) - i128::from(min) + 1) as u64;
+    let offset = interpreter.random().rand_range(0..choices);
+    Ok(Value::Number((i128::from(min) + i128::from(offset)) as f64))
+}
+
+/// Returns a map from the name of every global, natives included, to its current value.
+fn globals_map(interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value> {
+    let mut environment = interpreter.environment();
+    while let Some(enclosing) = environment.enclosing() {
+        environment = enclosing;
+    }
+    bindings_map(std::iter::once(environment))
+}
+
+/// Loads a native plugin, whose natives become globals from then on, even when it is loaded
+/// inside a block.
+fn load_plugin(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Plugins, "loadPlugin")?;
+    let path = string_argument("loadPlugin", &arguments[0])?;
+    crate::ffi::load_plugin(interpreter, Path::new(path))?;
+    Ok(Value::Nil)
+}
+
+/// Returns a map from the name of every variable visible in the calling scope, other than
+/// globals, to its current value. At the top level it is empty.
+fn locals_map(interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value> {
+    let scopes = std::iter::successors(Some(interpreter.environment()), |scope| scope.enclosing())
+        .filter(|scope| scope.enclosing().is_some());
+    bindings_map(scopes)
+}
+
+/// Builds a map of the bindings in `scopes`, innermost first, sorted by name. Names an
+/// inner scope shadows keep the inner value.
+fn bindings_map<'a>(scopes: impl Iterator<Item = &'a Environment>) -> Result<Value> {
+    let mut bindings = BTreeMap::new();
+    for scope in scopes {
+        for (name, value) in scope.values() {
+            bindings.entry(name).or_insert(value);
+        }
+    }
+    let mut map = Map::new();
+    for (name, value) in bindings {
+        map.insert(Value::String_(name.clone()), value.clone())?;
+    }
+    Ok(Value::map(map))
+}
@@ -0,0 +1,434 @@
+//! The Node.js addon, built with the `node` feature.
+//!
+//! Each `Interpreter` runs its scripts on a thread of its own, so `run` returns a promise and
+//! the event loop carries on meanwhile. Host functions are called back on the JavaScript
+//! thread while the script waits for their result. Values convert both ways: nil is `null`
+//! (and `undefined` becomes nil), bigints are `BigInt`s and maps are `Map`s.
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::interpreter::{Exit, Interpreter, Output};
+use crate::map::Map;
+use crate::native::NativeFunction;
+use crate::scanner::Scanner;
+use crate::value::Value;
+use napi::bindgen_prelude::External;
+use napi::threadsafe_function::{
+    ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::{
+    Env, Error, JsBigInt, JsDeferred, JsFunction, JsObject, JsUnknown, Ref, Result, Status,
+    ValueType,
+};
+use napi_derive::napi;
+use num_bigint::{BigInt, BigUint, Sign};
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// How deeply nested a JavaScript `Map` may be, which also stops one that contains itself.
+const MAX_DEPTH: usize = 64;
+
+/// A problem found in the source before it ran. `start` and `end` are byte offsets.
+#[napi(object, js_name = "Diagnostic")]
+pub struct NodeDiagnostic {
+    /// `"error"` or `"warning"`.
+    pub severity: String,
+    pub message: String,
+    pub line: u32,
+    pub start: u32,
+    pub end: u32,
+}
+
+#[napi(object)]
+pub struct RunResult {
+    /// What `rox run` would exit with: 0, 65 if the script did not compile, 70 after a
+    /// runtime error, or the code passed to `exit()`.
+    pub exit_code: i32,
+    /// Everything the script printed.
+    pub output: String,
+    pub diagnostics: Vec<NodeDiagnostic>,
+    /// The runtime error, if there was one.
+    pub error: Option<String>,
+}
+
+type Resolver = Box<dyn FnOnce(Env) -> Result<RunResult> + Send>;
+
+enum Job {
+    Run(String, JsDeferred<RunResult, Resolver>),
+    Register(
+        &'static str,
+        Option<usize>,
+        ThreadsafeFunction<Call, ErrorStrategy::Fatal>,
+    ),
+}
+
+/// A call from a script to a host function, and where to send what it returned or threw.
+struct Call {
+    arguments: Vec<Plain>,
+    reply: Sender<Reply>,
+}
+
+/// What a host function returned, or the message it threw.
+type Reply = std::result::Result<Plain, String>;
+
+/// Keeps a host function's callback alive until the function is garbage collected.
+struct Callback {
+    function: Ref<()>,
+    env: Env,
+}
+
+impl Drop for Callback {
+    fn drop(&mut self) {
+        let _ = self.function.unref(self.env);
+    }
+}
+
+/// A Lox interpreter whose globals last from one `run` to the next.
+#[napi(js_name = "Interpreter")]
+pub struct NodeInterpreter {
+    jobs: Sender<Job>,
+}
+
+#[napi]
+impl NodeInterpreter {
+    #[napi(constructor)]
+    pub fn new() -> Result<Self> {
+        let (jobs, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("rox".to_owned())
+            .spawn(move || work(&receiver))
+            .map_err(|error| Error::from_reason(error.to_string()))?;
+        Ok(Self { jobs })
+    }
+
+    /// Runs Lox source once the scripts already queued have finished.
+    #[napi(ts_return_type = "Promise<RunResult>")]
+    pub fn run(&self, env: Env, source: String) -> Result<JsObject> {
+        let (deferred, promise) = env.create_deferred()?;
+        self.send(Job::Run(source, deferred))?;
+        Ok(promise)
+    }
+
+    /// Defines a global native named `name` that calls `callback`. `arity` is the exact
+    /// number of arguments it takes, or any number if it is left out. A callback that
+    /// throws raises a runtime error in the script.
+    #[napi(ts_args_type = "name: string, callback: (...args: any[]) => any, arity?: number")]
+    pub fn register(
+        &self,
+        env: Env,
+        name: String,
+        callback: JsFunction,
+        arity: Option<u32>,
+    ) -> Result<()> {
+        let callback = Callback {
+            function: env.create_reference(callback)?,
+            env,
+        };
+        // Scripts call this through a thread-safe function, which must not throw, so it
+        // catches whatever the callback throws and sends it back instead.
+        let guard = env.create_function_from_closure(&name, move |ctx| {
+            let reply = ctx.get::<External<Sender<Reply>>>(0)?;
+            let arguments = (1..ctx.length)
+                .map(|i| ctx.get::<JsUnknown>(i))
+                .collect::<Result<Vec<_>>>()?;
+            let result = ctx
+                .env
+                .get_reference_value::<JsFunction>(&callback.function)
+                .and_then(|callback| callback.call(None, &arguments))
+                .map_err(|error| error.reason)
+                .and_then(|result| Plain::from_js(*ctx.env, result, 0));
+            let _ = reply.send(result);
+            ctx.env.get_undefined()
+        })?;
+        let mut function =
+            guard.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<Call>| {
+                let mut values = vec![ctx
+                    .env
+                    .create_external(ctx.value.reply, None)?
+                    .into_unknown()];
+                for argument in ctx.value.arguments {
+                    values.push(argument.into_js(ctx.env)?);
+                }
+                Ok(values)
+            })?;
+        // Only a running script, which holds a promise open anyway, can call it.
+        function.unref(&env)?;
+        // Natives are named with `&'static str`s, and hosts register a handful of them.
+        let name: &'static str = Box::leak(name.into_boxed_str());
+        let arity = arity.map(|arity| usize::try_from(arity).unwrap_or(usize::MAX));
+        self.send(Job::Register(name, arity, function))
+    }
+}
+
+impl NodeInterpreter {
+    fn send(&self, job: Job) -> Result<()> {
+        self.jobs
+            .send(job)
+            .map_err(|_| Error::new(Status::GenericFailure, "The interpreter has stopped."))
+    }
+}
+
+/// Runs Lox source in a fresh interpreter.
+#[napi(ts_return_type = "Promise<RunResult>")]
+// napi only registers exported functions outside test builds, which leaves this one unused.
+#[cfg_attr(test, allow(dead_code))]
+pub fn run(env: Env, source: String) -> Result<JsObject> {
+    NodeInterpreter::new()?.run(env, source)
+}
+
+/// Runs the jobs for one interpreter, in order, until the `Interpreter` object is dropped.
+fn work(jobs: &Receiver<Job>) {
+    let output = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(Output::new(output.clone()));
+    for job in jobs {
+        match job {
+            Job::Run(source, deferred) => {
+                let mut result = run_source(&mut interpreter, &source);
+                result.output = String::from_utf8_lossy(&output.0.take()).into_owned();
+                deferred.resolve(Box::new(move |_| Ok(result)));
+            }
+            Job::Register(name, arity, function) => {
+                let native = host_function(name, arity, function);
+                interpreter.define_global(name, Value::NativeFunction(native));
+            }
+        }
+    }
+}
+
+fn run_source(interpreter: &mut Interpreter, source: &str) -> RunResult {
+    let mut result = RunResult {
+        exit_code: 0,
+        output: String::new(),
+        diagnostics: Vec::new(),
+        error: None,
+    };
+    let mut scanner = Scanner::new(source.to_owned());
+    let tokens = scanner.scan_tokens();
+    let mut diagnostics = scanner.take_diagnostics();
+    // Parsing tokens around lexical errors mostly produces confusing follow-on errors.
+    let statements = if diagnostics.is_empty() {
+        let (statements, more) = crate::parse_tokens(tokens);
+        diagnostics.extend(more);
+        statements
+    } else {
+        Vec::new()
+    };
+    let compiled = !diagnostics.iter().any(Diagnostic::is_error);
+    result.diagnostics = diagnostics.iter().map(node_diagnostic).collect();
+    if !compiled {
+        result.exit_code = 65;
+    } else if let Err(error) = interpreter.interpret(&statements) {
+        if let Some(&Exit(code)) = error.downcast_ref::<Exit>() {
+            result.exit_code = code;
+        } else {
+            result.exit_code = 70;
+            result.error = Some(error.to_string());
+        }
+    }
+    result
+}
+
+fn node_diagnostic(diagnostic: &Diagnostic) -> NodeDiagnostic {
+    let number = |n: usize| u32::try_from(n).unwrap_or(u32::MAX);
+    NodeDiagnostic {
+        severity: match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+        .to_owned(),
+        message: diagnostic.message.clone(),
+        line: number(diagnostic.line.get()),
+        start: number(diagnostic.span.start),
+        end: number(diagnostic.span.end),
+    }
+}
+
+fn host_function(
+    name: &'static str,
+    arity: Option<usize>,
+    function: ThreadsafeFunction<Call, ErrorStrategy::Fatal>,
+) -> NativeFunction {
+    NativeFunction::with_closure(name, arity, move |_interpreter, arguments| {
+        let arguments = arguments
+            .iter()
+            .map(|argument| Plain::from_value(argument, &mut Vec::new()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let (reply, result) = mpsc::channel();
+        let status = function.call(
+            Call { arguments, reply },
+            ThreadsafeFunctionCallMode::Blocking,
+        );
+        if status != Status::Ok {
+            return Err(anyhow!("{name}() could not be called: {status}."));
+        }
+        match result.recv() {
+            Ok(Ok(value)) => value.into_value(),
+            Ok(Err(error)) => Err(anyhow!("{name}() threw {error}")),
+            Err(_) => Err(anyhow!("{name}() did not return.")),
+        }
+    })
+}
+
+/// A value on its way between threads. Lox values hold `Rc`s and JavaScript ones belong to
+/// their thread, so neither can cross.
+enum Plain {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    BigInt(BigInt),
+    Map(Vec<(Self, Self)>),
+}
+
+impl Plain {
+    /// `maps` holds the maps being converted, to catch one that contains itself.
+    fn from_value(value: &Value, maps: &mut Vec<*const ()>) -> anyhow::Result<Self> {
+        Ok(match value {
+            Value::Nil => Self::Nil,
+            Value::Bool(b) => Self::Bool(*b),
+            Value::Number(n) => Self::Number(*n),
+            Value::String_(s) => Self::String(s.clone()),
+            Value::BigInt(n) => Self::BigInt(n.clone()),
+            Value::Map(map) => {
+                let address = map.as_ptr() as *const ();
+                if maps.contains(&address) {
+                    return Err(anyhow!(
+                        "Cannot pass a map that contains itself to JavaScript."
+                    ));
+                }
+                maps.push(address);
+                let entries = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, item)| {
+                        Ok((Self::from_value(key, maps)?, Self::from_value(item, maps)?))
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                maps.pop();
+                Self::Map(entries)
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Cannot pass a {} to JavaScript.",
+                    value.type_name()
+                ))
+            }
+        })
+    }
+
+    fn into_value(self) -> anyhow::Result<Value> {
+        Ok(match self {
+            Self::Nil => Value::Nil,
+            Self::Bool(b) => Value::Bool(b),
+            Self::Number(n) => Value::Number(n),
+            Self::String(s) => Value::String_(s),
+            Self::BigInt(n) => Value::BigInt(n),
+            Self::Map(entries) => {
+                let mut map = Map::new();
+                for (key, item) in entries {
+                    map.insert(key.into_value()?, item.into_value()?)?;
+                }
+                Value::map(map)
+            }
+        })
+    }
+
+    fn into_js(self, env: Env) -> Result<JsUnknown> {
+        Ok(match self {
+            Self::Nil => env.get_null()?.into_unknown(),
+            Self::Bool(b) => env.get_boolean(b)?.into_unknown(),
+            Self::Number(n) => env.create_double(n)?.into_unknown(),
+            Self::String(s) => env.create_string_from_std(s)?.into_unknown(),
+            Self::BigInt(n) => {
+                let (sign, magnitude) = n.into_parts();
+                env.create_bigint_from_words(sign == Sign::Minus, magnitude.to_u64_digits())?
+                    .into_unknown()?
+            }
+            Self::Map(entries) => {
+                let map = map_class(env)?.new_instance::<JsUnknown>(&[])?;
+                let set: JsFunction = map.get_named_property("set")?;
+                for (key, item) in entries {
+                    set.call(Some(&map), &[key.into_js(env)?, item.into_js(env)?])?;
+                }
+                map.into_unknown()
+            }
+        })
+    }
+
+    fn from_js(env: Env, value: JsUnknown, depth: usize) -> std::result::Result<Self, String> {
+        Self::try_from_js(env, value, depth).map_err(|error| error.reason)
+    }
+
+    fn try_from_js(env: Env, value: JsUnknown, depth: usize) -> Result<Self> {
+        let type_name = match value.get_type()? {
+            ValueType::Undefined | ValueType::Null => return Ok(Self::Nil),
+            ValueType::Boolean => return Ok(Self::Bool(value.coerce_to_bool()?.get_value()?)),
+            ValueType::Number => return Ok(Self::Number(value.coerce_to_number()?.get_double()?)),
+            ValueType::String => {
+                return Ok(Self::String(
+                    value.coerce_to_string()?.into_utf8()?.into_owned()?,
+                ))
+            }
+            ValueType::BigInt => {
+                // SAFETY: `value` has just been checked to be a bigint.
+                let mut n = unsafe { value.cast::<JsBigInt>() };
+                let (negative, words) = n.get_words()?;
+                let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+                let sign = if negative { Sign::Minus } else { Sign::Plus };
+                return Ok(Self::BigInt(BigInt::from_biguint(
+                    sign,
+                    BigUint::from_bytes_le(&bytes),
+                )));
+            }
+            ValueType::Object if value.instanceof(map_class(env)?)? => {
+                if depth == MAX_DEPTH {
+                    return Err(Error::from_reason(format!(
+                        "Cannot convert a Map nested more than {MAX_DEPTH} deep to a Lox value."
+                    )));
+                }
+                let array: JsFunction = env
+                    .get_global()?
+                    .get_named_property::<JsFunction>("Array")?
+                    .coerce_to_object()?
+                    .get_named_property("from")?;
+                let entries = array.call(None, &[value])?.coerce_to_object()?;
+                let mut map = Vec::new();
+                for i in 0..entries.get_array_length()? {
+                    let entry: JsObject = entries.get_element(i)?;
+                    let key = Self::try_from_js(env, entry.get_element(0)?, depth + 1)?;
+                    let item = Self::try_from_js(env, entry.get_element(1)?, depth + 1)?;
+                    map.push((key, item));
+                }
+                return Ok(Self::Map(map));
+            }
+            ValueType::Function => "a function",
+            ValueType::Symbol => "a symbol",
+            _ => "an object",
+        };
+        Err(Error::from_reason(format!(
+            "Cannot convert {type_name} to a Lox value."
+        )))
+    }
+}
+
+fn map_class(env: Env) -> Result<JsFunction> {
+    env.get_global()?.get_named_property("Map")
+}
+
+/// Collects what scripts print, for `RunResult.output`.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,53 +1,111 @@
-use crate::ast::{Expr, Stmt};
-use crate::error;
+use crate::ast::{Expr, ExprKind, Stmt, StmtKind};
+use crate::cfg::Cfg;
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
 use crate::tokens::TokenType::{
-    Bang, BangEqual, Class, Else, Eof, Equal, EqualEqual, False, For, Fun, Greater, GreaterEqual,
-    Identifier, If, LeftBrace, LeftParen, Less, LessEqual, Minus, Nil, Number, Or, Plus, Print,
-    Return, RightBrace, RightParen, Semicolon, Slash, Star, String_, True, Var, While,
+    And, Bang, BangEqual, Break, Class, Colon, Comma, Continue, Do, Dot, Else, Eof, Equal,
+    EqualEqual, False, For, Fun, Greater, GreaterEqual, GreaterGreater, Identifier, If, In,
+    LeftBrace, LeftBracket, LeftParen, Less, LessEqual, LessLess, Loop, Minus, Nil, Number, Or,
+    Plus, Print, Return, RightBrace, RightBracket, RightParen, Semicolon, Slash, Star, String_,
+    True, Var, While,
 };
 use crate::tokens::{Literal, Token, TokenType};
 use anyhow::Result;
+use std::num::NonZeroUsize;
+
+/// How deeply statements and expressions may nest.
+const MAX_DEPTH: usize = 100;
+
+/// How tall the tree under one chain of binary operators, such as `a + b + c`, may grow.
+/// Chains parse in a loop, so they get a limit of their own, far above `MAX_DEPTH`.
+const MAX_HEIGHT: usize = 1000;
+
+/// How many arguments a call may pass.
+const MAX_ARGUMENTS: usize = 255;
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    depth: usize,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+    pub fn new(mut tokens: Vec<Token>) -> Self {
+        // The parser stops at `Eof`, so make sure there is one.
+        if tokens.last().is_none_or(|token| token.type_ != Eof) {
+            let (line, end) = tokens
+                .last()
+                .map_or((NonZeroUsize::MIN, 0), |token| (token.line, token.span.end));
+            tokens.push(Token::new(Eof, "", None, line, Span::new(end, end)));
+        }
+        Self {
+            tokens,
+            current: 0,
+            depth: 0,
+            diagnostics: Vec::new(),
+        }
     }
 
     pub fn parse(&mut self) -> Vec<Stmt> {
         let mut statements = Vec::new();
-        loop {
+        while !self.is_at_end() {
             if let Some(stmt) = self.declaration() {
                 statements.push(stmt);
             }
-            if self.is_at_end() {
-                break;
-            }
+        }
+        if !self.diagnostics.iter().any(Diagnostic::is_error) {
+            self.warn_unreachable(&statements);
         }
         statements
     }
 
+    /// Warns once about each stretch of statements that control flow can never reach.
+    fn warn_unreachable(&mut self, statements: &[Stmt]) {
+        for span in Cfg::build(statements).unreachable_code() {
+            let first = self
+                .tokens
+                .partition_point(|token| token.span.start < span.start)
+                .min(self.tokens.len() - 1);
+            self.diagnostics.push(Diagnostic {
+                span,
+                ..Diagnostic::warning_at_token(&self.tokens[first], "Unreachable code.")
+            });
+        }
+    }
+
+    /// Parses the tokens as a single expression with nothing after it.
+    pub fn parse_expression(&mut self) -> Option<Expr> {
+        let expr = self.expression().ok()?;
+        if !self.is_at_end() {
+            let token = self.peek();
+            self.error_at_token(&token, "Expect end of expression.");
+            return None;
+        }
+        Some(expr)
+    }
+
+    /// Returns the errors and warnings reported so far, leaving none behind.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
     fn declaration(&mut self) -> Option<Stmt> {
-        if self.matches(&[Var]) {
-            if let Ok(stmt) = self.var_declaration() {
-                Some(stmt)
-            } else {
-                self.synchronize();
-                None
-            }
-        } else if let Ok(stmt) = self.statement() {
-            Some(stmt)
+        let depth = self.depth;
+        let result = if self.matches(&[Var]) {
+            self.var_declaration()
         } else {
+            self.statement()
+        };
+        if result.is_err() {
+            self.depth = depth;
             self.synchronize();
-            None
         }
+        result.ok()
     }
 
     fn var_declaration(&mut self) -> Result<Stmt> {
+        let start = self.previous().span;
         let name = self.consume(&Identifier, "Expect variable name.")?;
         let initializer = if self.matches(&[Equal]) {
             self.expression().ok()
@@ -55,26 +113,45 @@ impl Parser {
             None
         };
         self.consume(&Semicolon, "Expect ';' after variable declaration.")?;
-        Ok(Stmt::Var(name, initializer))
+        Ok(Stmt::new(
+            StmtKind::Var(name, initializer),
+            self.span_from(start),
+        ))
     }
 
     fn statement(&mut self) -> Result<Stmt> {
-        if self.matches(&[For]) {
+        self.nested(Self::unnested_statement)
+    }
+
+    fn unnested_statement(&mut self) -> Result<Stmt> {
+        if self.matches(&[Break, Continue]) {
+            self.loop_control_statement()
+        } else if self.matches(&[Do]) {
+            self.do_while_statement()
+        } else if self.matches(&[For]) {
             self.for_statement()
         } else if self.matches(&[If]) {
             self.if_statement()
+        } else if self.matches(&[Loop]) {
+            self.loop_statement()
         } else if self.matches(&[Print]) {
             self.print_statement()
         } else if self.matches(&[While]) {
             self.while_statement()
         } else if self.matches(&[LeftBrace]) {
-            Ok(Stmt::Block(self.block()?))
+            let start = self.previous().span;
+            let statements = self.block()?;
+            Ok(Stmt::new(
+                StmtKind::Block(statements),
+                self.span_from(start),
+            ))
         } else {
             self.expression_statement()
         }
     }
 
     fn for_statement(&mut self) -> Result<Stmt> {
+        let start = self.previous().span;
         self.consume(&LeftParen, "Expect '(' after 'for'.")?;
         let initializer = if self.matches(&[Semicolon]) {
             None
@@ -84,37 +161,116 @@ impl Parser {
             self.expression_statement().ok()
         };
         let condition = if self.check(&Semicolon) {
-            Expr::Literal(Literal::Bool(true))
+            Expr::new(ExprKind::Literal(Literal::Bool(true)), self.peek().span)
         } else {
-            self.expression()?
+            self.sequence()?
         };
         self.consume(&Semicolon, "Expect ';' after loop condition.")?;
         let increment = if self.check(&RightParen) {
             None
         } else {
-            self.expression().ok()
+            self.sequence().ok()
         };
         self.consume(&RightParen, "Expect ')' after for clauses.")?;
-        let mut body = self.statement()?;
-        if let Some(increment) = increment {
-            body = Stmt::Block(vec![body, Stmt::Expression(increment)]);
-        }
-        body = Stmt::While(condition, Box::new(body));
+        let body = self.statement()?;
+        let span = self.span_from(start);
+        let mut body = Stmt::new(StmtKind::While(condition, Box::new(body), increment), span);
         if let Some(initializer) = initializer {
-            body = Stmt::Block(vec![initializer, body]);
+            body = Stmt::new(StmtKind::Block(vec![initializer, body]), span);
         }
         Ok(body)
     }
 
     fn while_statement(&mut self) -> Result<Stmt> {
+        let start = self.previous().span;
         self.consume(&LeftParen, "Expect '(' after 'while'.")?;
         let condition = self.expression()?;
         self.consume(&RightParen, "Expect ')' after condition.")?;
         let body = self.statement()?;
-        Ok(Stmt::While(condition, Box::new(body)))
+        Ok(Stmt::new(
+            StmtKind::While(condition, Box::new(body), None),
+            self.span_from(start),
+        ))
+    }
+
+    /// Parses `loop { ... }`, which is sugar for `while (true) { ... }`. A loop with no
+    /// reachable `break` can only end by exiting the script, so it draws a warning.
+    fn loop_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous();
+        let start = keyword.span;
+        let condition = Expr::new(ExprKind::Literal(Literal::Bool(true)), start);
+        let body = self.statement()?;
+        if !Self::has_reachable_break(&body) {
+            self.diagnostics.push(Diagnostic::warning_at_token(
+                &keyword,
+                "This loop has no reachable 'break'.",
+            ));
+        }
+        Ok(Stmt::new(
+            StmtKind::While(condition, Box::new(body), None),
+            self.span_from(start),
+        ))
+    }
+
+    /// Whether `stmt` can reach a `break` belonging to the loop around it. Breaks in nested
+    /// loops and statements after an unconditional `break` or `continue` do not count.
+    fn has_reachable_break(stmt: &Stmt) -> bool {
+        match &stmt.kind {
+            StmtKind::Break(_) => true,
+            StmtKind::Block(statements) => {
+                for statement in statements {
+                    if Self::has_reachable_break(statement) {
+                        return true;
+                    }
+                    if matches!(statement.kind, StmtKind::Continue(_)) {
+                        return false;
+                    }
+                }
+                false
+            }
+            StmtKind::If(_, then_branch, else_branch) => {
+                Self::has_reachable_break(then_branch)
+                    || else_branch
+                        .as_ref()
+                        .as_ref()
+                        .is_some_and(Self::has_reachable_break)
+            }
+            _ => false,
+        }
+    }
+
+    fn do_while_statement(&mut self) -> Result<Stmt> {
+        let start = self.previous().span;
+        let body = self.statement()?;
+        self.consume(&While, "Expect 'while' after do-while body.")?;
+        self.consume(&LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(&RightParen, "Expect ')' after condition.")?;
+        self.consume(&Semicolon, "Expect ';' after do-while condition.")?;
+        Ok(Stmt::new(
+            StmtKind::DoWhile(Box::new(body), condition),
+            self.span_from(start),
+        ))
+    }
+
+    /// Parses `break;` or `continue;`. The resolver checks they are inside a loop.
+    fn loop_control_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.previous();
+        let start = keyword.span;
+        self.consume(
+            &Semicolon,
+            &format!("Expect ';' after '{}'.", keyword.lexeme),
+        )?;
+        let kind = if keyword.type_ == Break {
+            StmtKind::Break(keyword)
+        } else {
+            StmtKind::Continue(keyword)
+        };
+        Ok(Stmt::new(kind, self.span_from(start)))
     }
 
     fn if_statement(&mut self) -> Result<Stmt> {
+        let start = self.previous().span;
         self.consume(&LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
         self.consume(&RightParen, "Expect ')' after if condition.")?;
@@ -124,37 +280,177 @@ impl Parser {
         } else {
             None
         };
-        Ok(Stmt::If(
-            condition,
-            Box::new(then_branch),
-            Box::new(else_branch),
+        Ok(Stmt::new(
+            StmtKind::If(condition, Box::new(then_branch), Box::new(else_branch)),
+            self.span_from(start),
         ))
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>> {
         let mut statements = Vec::new();
-        loop {
+        while !self.check(&RightBrace) && !self.is_at_end() {
             if let Some(stmt) = self.declaration() {
                 statements.push(stmt);
             }
-            if self.check(&RightBrace) || self.is_at_end() {
-                break;
-            }
         }
-        self.consume(&RightBrace, "Expect ';' after block.")?;
+        self.consume(&RightBrace, "Expect '}' after block.")?;
         Ok(statements)
     }
 
     fn print_statement(&mut self) -> Result<Stmt> {
+        if self.is_print_call() {
+            // `print(a, b);` and `print();` call the `print` native, while `print(a);` stays a
+            // print statement so it still ends the line.
+            self.current -= 1;
+            return self.expression_statement();
+        }
+        let start = self.previous().span;
         let value = self.expression()?;
         self.consume(&Semicolon, "Expect ';' after value.")?;
-        Ok(Stmt::Print(value))
+        Ok(Stmt::new(StmtKind::Print(value), self.span_from(start)))
+    }
+
+    /// Checks whether the tokens after `print` are a parenthesized list of other than one
+    /// argument that makes up the whole statement, without consuming anything.
+    fn is_print_call(&self) -> bool {
+        if !self.check(&LeftParen) {
+            return false;
+        }
+        let mut depth = 0;
+        let mut arguments = 1;
+        for (i, token) in self.tokens.iter().enumerate().skip(self.current) {
+            match token.type_ {
+                LeftParen | LeftBrace | LeftBracket => depth += 1,
+                RightParen if depth == 1 => {
+                    if i == self.current + 1 {
+                        arguments = 0;
+                    }
+                    return arguments != 1
+                        && self
+                            .tokens
+                            .get(i + 1)
+                            .is_some_and(|next| next.type_ == Semicolon);
+                }
+                RightParen | RightBrace | RightBracket => depth -= 1,
+                Comma if depth == 1 => arguments += 1,
+                Eof => return false,
+                _ => {}
+            }
+        }
+        false
     }
 
     fn expression_statement(&mut self) -> Result<Stmt> {
-        let expr = self.expression()?;
+        let expr = self.sequence()?;
+        let start = expr.span;
         self.consume(&Semicolon, "Expect ';' after expression.")?;
-        Ok(Stmt::Expression(expr))
+        let span = self.span_from(start);
+        Ok(self
+            .multiple_assignment(&expr, span)
+            .unwrap_or_else(|| Stmt::new(StmtKind::Expression(expr), span)))
+    }
+
+    /// Turns `a, b = b, a;`, which parses as a comma expression with one assignment inside,
+    /// into a block that evaluates every value before assigning any of them:
+    ///
+    /// ```text
+    /// { var $0 = b; var $1 = a; a = $0; b = $1; }
+    /// ```
+    ///
+    /// `$` cannot start a Lox identifier, so the temporaries cannot clash with the script's
+    /// own variables. Returns `None` for any other expression.
+    fn multiple_assignment(&mut self, expr: &Expr, span: Span) -> Option<Stmt> {
+        let items = expr.comma_operands();
+        let position = items.iter().position(|item| {
+            matches!(
+                item.kind,
+                ExprKind::Assign(..) | ExprKind::SetGlobal(..) | ExprKind::SetIndex(..)
+            )
+        })?;
+        let is_target = |item: &&Expr| {
+            matches!(
+                item.kind,
+                ExprKind::Variable(_) | ExprKind::Global(_) | ExprKind::Index(..)
+            )
+        };
+        if position == 0 || !items[..position].iter().all(is_target) {
+            return None;
+        }
+        let (target, value, token) = match &items[position].kind {
+            ExprKind::Assign(name, value) => (
+                Expr::new(ExprKind::Variable(name.clone()), name.span),
+                value,
+                name,
+            ),
+            ExprKind::SetGlobal(name, value) => (
+                Expr::new(
+                    ExprKind::Global(name.clone()),
+                    Span::new(items[position].span.start, name.span.end),
+                ),
+                value,
+                name,
+            ),
+            ExprKind::SetIndex(object, bracket, index, value) => (
+                Expr::new(
+                    ExprKind::Index(object.clone(), bracket.clone(), index.clone()),
+                    object.span.to(bracket.span),
+                ),
+                value,
+                bracket,
+            ),
+            _ => unreachable!("position found an assignment"),
+        };
+        let mut targets: Vec<Expr> = items[..position].iter().map(|&item| item.clone()).collect();
+        targets.push(target);
+        let values: Vec<&Expr> = std::iter::once(&**value)
+            .chain(items[position + 1..].iter().copied())
+            .collect();
+        if targets.len() != values.len() {
+            let token = token.clone();
+            self.error_at_token(
+                &token,
+                &format!(
+                    "Expected {} values to assign but got {}.",
+                    targets.len(),
+                    values.len()
+                ),
+            );
+            return None;
+        }
+        let temporary = |i: usize, value: &Expr| {
+            Token::new(Identifier, &format!("${i}"), None, token.line, value.span)
+        };
+        let mut statements = Vec::new();
+        for (i, value) in values.iter().enumerate() {
+            statements.push(Stmt::new(
+                StmtKind::Var(temporary(i, value), Some((*value).clone())),
+                value.span,
+            ));
+        }
+        for (i, (target, value)) in targets.into_iter().zip(&values).enumerate() {
+            let temporary = Expr::new(ExprKind::Variable(temporary(i, value)), value.span);
+            let span = target.span.to(value.span);
+            let assignment = match target.kind {
+                ExprKind::Variable(name) => ExprKind::Assign(name, Box::new(temporary)),
+                ExprKind::Global(name) => ExprKind::SetGlobal(name, Box::new(temporary)),
+                ExprKind::Index(object, bracket, index) => {
+                    ExprKind::SetIndex(object, bracket, index, Box::new(temporary))
+                }
+                _ => unreachable!("only targets were collected"),
+            };
+            statements.push(Stmt::new(
+                StmtKind::Expression(Expr::new(assignment, span)),
+                span,
+            ));
+        }
+        Some(Stmt::new(StmtKind::Block(statements), span))
+    }
+
+    /// Parses a comma-separated sequence, which evaluates each operand in turn and yields the
+    /// last. Only expression statements and `for` clauses allow one, so commas elsewhere, as
+    /// in `print (a), (b);` or call arguments, keep their usual meaning.
+    fn sequence(&mut self) -> Result<Expr> {
+        self.left_associative(&[Comma], Self::assignment, ExprKind::Binary)
     }
 
     fn expression(&mut self) -> Result<Expr> {
@@ -165,12 +461,23 @@ impl Parser {
         let expr = self.or()?;
         if self.matches(&[Equal]) {
             let equals = self.previous();
-            let value = self.assignment()?;
-            if let Expr::Variable(name) = expr {
-                Ok(Expr::Assign(name, Box::new(value)))
-            } else {
-                error(equals.line, "Invalid assignment taarget.");
-                Ok(expr)
+            let value = self.nested(Self::assignment)?;
+            let span = expr.span.to(value.span);
+            match expr.kind {
+                ExprKind::Variable(name) => {
+                    Ok(Expr::new(ExprKind::Assign(name, Box::new(value)), span))
+                }
+                ExprKind::Index(object, bracket, index) => Ok(Expr::new(
+                    ExprKind::SetIndex(object, bracket, index, Box::new(value)),
+                    span,
+                )),
+                ExprKind::Global(name) => {
+                    Ok(Expr::new(ExprKind::SetGlobal(name, Box::new(value)), span))
+                }
+                kind => {
+                    self.error_at_token(&equals, "Invalid assignment target.");
+                    Ok(Expr::new(kind, expr.span))
+                }
             }
         } else {
             Ok(expr)
@@ -178,103 +485,245 @@ impl Parser {
     }
 
     fn or(&mut self) -> Result<Expr> {
-        let mut expr = self.and()?;
-        while self.matches(&[Or]) {
-            let operator = self.previous();
-            let right = self.and()?;
-            expr = Expr::Logical(Box::new(expr), operator, Box::new(right));
-        }
-        Ok(expr)
+        self.left_associative(&[Or], Self::and, ExprKind::Logical)
     }
 
     fn and(&mut self) -> Result<Expr> {
-        let mut expr = self.equality()?;
-        while self.matches(&[Or]) {
-            let operator = self.previous();
-            let right = self.equality()?;
-            expr = Expr::Logical(Box::new(expr), operator, Box::new(right));
-        }
-        Ok(expr)
+        self.left_associative(&[And], Self::equality, ExprKind::Logical)
     }
 
     fn equality(&mut self) -> Result<Expr> {
-        let mut expr = self.comparison()?;
-        while self.matches(&[BangEqual, EqualEqual]) {
-            let operator = self.previous();
-            let right = self.comparison()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
-        }
-        Ok(expr)
+        self.left_associative(&[BangEqual, EqualEqual], Self::membership, ExprKind::Binary)
+    }
+
+    fn membership(&mut self) -> Result<Expr> {
+        self.left_associative(&[In], Self::comparison, ExprKind::Binary)
     }
 
     fn comparison(&mut self) -> Result<Expr> {
-        let mut expr = self.term()?;
-        while self.matches(&[Greater, GreaterEqual, Less, LessEqual]) {
-            let operator = self.previous();
-            let right = self.term()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
-        }
-        Ok(expr)
+        self.left_associative(
+            &[Greater, GreaterEqual, Less, LessEqual],
+            Self::shift,
+            ExprKind::Binary,
+        )
+    }
+
+    fn shift(&mut self) -> Result<Expr> {
+        self.left_associative(&[LessLess, GreaterGreater], Self::term, ExprKind::Binary)
     }
 
     fn term(&mut self) -> Result<Expr> {
-        let mut expr = self.factor()?;
-        while self.matches(&[Plus, Minus]) {
-            let operator = self.previous();
-            let right = self.factor()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
-        }
-        Ok(expr)
+        self.left_associative(&[Plus, Minus], Self::factor, ExprKind::Binary)
     }
 
     fn factor(&mut self) -> Result<Expr> {
-        let mut expr = self.unary()?;
-        while self.matches(&[Slash, Star]) {
-            let operator = self.previous();
-            let right = self.unary()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
-        }
-        Ok(expr)
+        self.left_associative(&[Slash, Star], Self::unary, ExprKind::Binary)
     }
 
     fn unary(&mut self) -> Result<Expr> {
         if self.matches(&[Bang, Minus]) {
             let operator = self.previous();
-            let right = self.unary()?;
-            Ok(Expr::Unary(operator, Box::new(right)))
+            let right = self.nested(Self::unary)?;
+            let span = operator.span.to(right.span);
+            Ok(Expr::new(ExprKind::Unary(operator, Box::new(right)), span))
         } else {
-            self.primary()
+            self.call()
+        }
+    }
+
+    /// Parses a primary expression followed by any calls, property accesses and indexes.
+    /// Each one nests the tree a level deeper.
+    fn call(&mut self) -> Result<Expr> {
+        let depth = self.depth;
+        let mut expr = self.primary()?;
+        loop {
+            if self.check(&LeftParen) || self.check(&Dot) || self.check(&LeftBracket) {
+                self.deeper()?;
+            }
+            if self.matches(&[LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.matches(&[Dot]) {
+                let name = self.consume(&Identifier, "Expect property name after '.'.")?;
+                let span = expr.span.to(name.span);
+                expr = Expr::new(ExprKind::Get(Box::new(expr), name), span);
+            } else if self.matches(&[LeftBracket]) {
+                let index = self.nested(Self::expression)?;
+                let bracket = self.consume(&RightBracket, "Expect ']' after index.")?;
+                let span = expr.span.to(bracket.span);
+                expr = Expr::new(
+                    ExprKind::Index(Box::new(expr), bracket, Box::new(index)),
+                    span,
+                );
+            } else {
+                break;
+            }
         }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr> {
+        let mut arguments = Vec::new();
+        while !self.check(&RightParen) {
+            // Reported once, without giving up on the call, so parsing carries on after it.
+            if arguments.len() == MAX_ARGUMENTS {
+                let token = self.peek();
+                self.error_at_token(
+                    &token,
+                    &format!("Can't have more than {MAX_ARGUMENTS} arguments."),
+                );
+            }
+            arguments.push(self.nested(Self::expression)?);
+            // A trailing comma before the closing paren is allowed.
+            if !self.matches(&[Comma]) {
+                break;
+            }
+        }
+        let paren = self.consume(&RightParen, "Expect ')' after arguments.")?;
+        let span = callee.span.to(paren.span);
+        Ok(Expr::new(
+            ExprKind::Call(Box::new(callee), paren, arguments),
+            span,
+        ))
     }
 
     fn primary(&mut self) -> Result<Expr> {
         if self.matches(&[False]) {
-            return Ok(Expr::Literal(Literal::Bool(false)));
+            return Ok(self.literal(Literal::Bool(false)));
         }
         if self.matches(&[True]) {
-            return Ok(Expr::Literal(Literal::Bool(true)));
+            return Ok(self.literal(Literal::Bool(true)));
         }
         if self.matches(&[Nil]) {
-            return Ok(Expr::Literal(Literal::Nil));
+            return Ok(self.literal(Literal::Nil));
         }
         if self.matches(&[Number, String_]) {
-            return Ok(Expr::Literal(match self.previous().literal {
-                Some(l) => l,
-                None => Literal::Nil,
-            }));
+            let literal = self.previous().literal.unwrap_or(Literal::Nil);
+            return Ok(self.literal(literal));
+        }
+        if self.check(&Identifier) && self.peek().lexeme == "global" && self.check_next(&Dot) {
+            let start = self.advance().span;
+            self.advance();
+            let name = self.consume(&Identifier, "Expect global name after 'global.'.")?;
+            return Ok(Expr::new(ExprKind::Global(name), self.span_from(start)));
         }
-        if self.matches(&[Identifier]) {
-            return Ok(Expr::Variable(self.previous()));
+        if self.matches(&[Identifier, Print]) {
+            let name = self.previous();
+            let span = name.span;
+            return Ok(Expr::new(ExprKind::Variable(name), span));
+        }
+        if self.matches(&[LeftBrace]) {
+            return self.nested(Self::map);
         }
         if self.matches(&[LeftParen]) {
-            let expr = self.expression()?;
+            let start = self.previous().span;
+            let expr = self.nested(Self::expression)?;
             self.consume(&RightParen, "Expect `)` after expression")?;
-            return Ok(Expr::Grouping(Box::new(expr)));
+            return Ok(Expr::new(
+                ExprKind::Grouping(Box::new(expr)),
+                self.span_from(start),
+            ));
         }
-        crate::error_at_token(&self.peek(), "Expect expression");
+        let token = self.peek();
+        self.error_at_token(&token, "Expect expression");
         Err(anyhow!("Parse error"))
     }
 
+    /// Parses a map literal. A `{` in expression position always starts a map; at the start
+    /// of a statement it starts a block instead.
+    fn map(&mut self) -> Result<Expr> {
+        let brace = self.previous();
+        let start = brace.span;
+        let mut entries = Vec::new();
+        while !self.check(&RightBrace) {
+            let key = if self.matches(&[LeftBracket]) {
+                let key = self.expression()?;
+                self.consume(&RightBracket, "Expect ']' after computed key.")?;
+                key
+            } else if self.matches(&[Number, String_]) {
+                let literal = self.previous().literal.unwrap_or(Literal::Nil);
+                self.literal(literal)
+            } else {
+                let token = self.peek();
+                self.error_at_token(&token, "Expect map key.");
+                return Err(anyhow!("Parse error"));
+            };
+            self.consume(&Colon, "Expect ':' after map key.")?;
+            let value = self.expression()?;
+            entries.push((key, value));
+            if !self.matches(&[Comma]) {
+                break;
+            }
+        }
+        self.consume(&RightBrace, "Expect '}' after map entries.")?;
+        Ok(Expr::new(
+            ExprKind::Map(brace, entries),
+            self.span_from(start),
+        ))
+    }
+
+    fn literal(&self, literal: Literal) -> Expr {
+        Expr::new(ExprKind::Literal(literal), self.previous().span)
+    }
+
+    /// Parses a left-associative chain such as `a + b - c`. The chain is parsed in a loop,
+    /// but each link nests the tree one level deeper, so the tree's height is checked too:
+    /// the passes that walk it later recurse.
+    fn left_associative(
+        &mut self,
+        operators: &[TokenType],
+        operand: fn(&mut Self) -> Result<Expr>,
+        kind: fn(Box<Expr>, Token, Box<Expr>) -> ExprKind,
+    ) -> Result<Expr> {
+        let mut expr = operand(self)?;
+        let mut height = None;
+        while self.matches(operators) {
+            let operator = self.previous();
+            let right = operand(self)?;
+            let left_height = height.unwrap_or_else(|| expr.height());
+            let new_height = left_height.max(right.height()) + 1;
+            if new_height > MAX_HEIGHT {
+                self.error_at_token(&operator, "Too many operators in one expression.");
+                return Err(anyhow!("Parse error"));
+            }
+            height = Some(new_height);
+            let span = expr.span.to(right.span);
+            expr = Expr::new(kind(Box::new(expr), operator, Box::new(right)), span);
+        }
+        Ok(expr)
+    }
+
+    /// Runs `parse` one level deeper. On failure the depth is left as it is; `declaration`
+    /// puts it back before parsing the next declaration.
+    fn nested<T>(&mut self, parse: fn(&mut Self) -> Result<T>) -> Result<T> {
+        self.deeper()?;
+        let result = parse(self)?;
+        self.depth -= 1;
+        Ok(result)
+    }
+
+    /// Fails once the syntax tree would get deep enough to risk overflowing the stack here
+    /// or in the passes that walk it later.
+    fn deeper(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            let token = self.peek();
+            self.error_at_token(&token, "Too much nesting.");
+            return Err(anyhow!("Parse error"));
+        }
+        Ok(())
+    }
+
+    /// Returns the span from `start` to the end of the most recently consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous().span)
+    }
+
+    fn error_at_token(&mut self, token: &Token, message: &str) {
+        self.diagnostics
+            .push(Diagnostic::error_at_token(token, message));
+    }
+
     fn synchronize(&mut self) {
         self.advance();
         while !self.is_at_end() {
@@ -282,7 +731,7 @@ impl Parser {
                 return;
             }
             match self.peek().type_ {
-                Class | Fun | Var | For | If | While | Print | Return => {
+                Class | Fun | Var | For | If | While | Do | Loop | Print | Return => {
                     return;
                 }
                 _ => {}
@@ -305,7 +754,8 @@ impl Parser {
         if self.check(type_) {
             Ok(self.advance())
         } else {
-            crate::error_at_token(&self.peek(), message);
+            let token = self.peek();
+            self.error_at_token(&token, message);
             Err(anyhow!("Parse error"))
         }
     }
@@ -318,6 +768,13 @@ impl Parser {
         }
     }
 
+    /// Like `check`, but for the token after the next one.
+    fn check_next(&self, type_: &TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| &token.type_ == type_)
+    }
+
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
             self.current += 1;
@@ -333,7 +790,8 @@ impl Parser {
         self.tokens[self.current].clone()
     }
 
+    /// The most recently consumed token, or the first token if none has been consumed yet.
     fn previous(&self) -> Token {
-        self.tokens[self.current - 1].clone()
+        self.tokens[self.current.saturating_sub(1)].clone()
     }
 }
@@ -0,0 +1,224 @@
+//! The `rox` Python module, built with the `python` feature.
+//!
+//! `print` output goes to Python's `sys.stdout`, so notebooks and `contextlib.redirect_stdout`
+//! see it. Values convert both ways: nil is `None`, numbers are `float`s, bigints `int`s,
+//! decimals `decimal.Decimal`s and maps `dict`s. Python `int`s become numbers when a float
+//! holds them exactly and bigints otherwise.
+
+// PyO3 0.22's macros check a `gil-refs` cfg and convert `PyErr`s into themselves, and
+// integers are only cast to f64 once they are known to fit.
+#![allow(
+    unexpected_cfgs,
+    clippy::useless_conversion,
+    clippy::cast_precision_loss
+)]
+
+use crate::interpreter::{Exit, Interpreter, Output};
+use crate::map::Map;
+use crate::native::NativeFunction;
+use crate::value::Value;
+use num_bigint::BigInt;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyKeyError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyString, PyTuple};
+use rust_decimal::Decimal;
+use std::io::{self, Write};
+
+create_exception!(
+    rox,
+    CompileError,
+    PyException,
+    "Lox source that did not compile."
+);
+create_exception!(rox, RuntimeError, PyException, "A Lox runtime error.");
+
+/// The largest integer below which every integer is exactly representable as an f64.
+const MAX_SAFE_INTEGER: u64 = 1 << 53;
+
+/// A Lox interpreter whose globals last from one `run` to the next.
+#[pyclass(unsendable, name = "Interpreter", module = "rox")]
+struct PyInterpreter {
+    interpreter: Interpreter,
+}
+
+#[pymethods]
+impl PyInterpreter {
+    #[new]
+    fn new() -> Self {
+        Self {
+            interpreter: Interpreter::with_output(Output::new(PythonStdout)),
+        }
+    }
+
+    /// Runs Lox source, returning the code passed to `exit()`, or 0 if it ran to the end.
+    fn run(&mut self, source: &str) -> PyResult<i32> {
+        let statements = crate::parse(source).map_err(|diagnostics| {
+            let diagnostics: Vec<_> = diagnostics.iter().map(ToString::to_string).collect();
+            CompileError::new_err(diagnostics.join("\n"))
+        })?;
+        match self.interpreter.interpret(&statements) {
+            Ok(()) => Ok(0),
+            Err(error) => match error.downcast_ref::<Exit>() {
+                Some(&Exit(code)) => Ok(code),
+                None => Err(RuntimeError::new_err(error.to_string())),
+            },
+        }
+    }
+
+    /// Defines a global native named `name` that calls `function`. `arity` is the exact
+    /// number of arguments it takes, or `None` for any number.
+    #[pyo3(signature = (name, function, arity = None))]
+    fn register(&mut self, name: &str, function: PyObject, arity: Option<usize>) {
+        // Natives are named with `&'static str`s, and hosts register a handful of them.
+        let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        let native = NativeFunction::with_closure(name, arity, move |_interpreter, arguments| {
+            Python::with_gil(|py| {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| to_python(py, argument, &mut Vec::new()))
+                    .collect::<PyResult<Vec<_>>>()?;
+                let result = function.call1(py, PyTuple::new_bound(py, arguments))?;
+                from_python(result.bind(py), &mut Vec::new())
+            })
+            .map_err(|error| anyhow!("{name}() raised {error}"))
+        });
+        self.interpreter
+            .define_global(name, Value::NativeFunction(native));
+    }
+
+    fn __getitem__(&self, py: Python<'_>, name: &str) -> PyResult<PyObject> {
+        let value = self
+            .interpreter
+            .global(name)
+            .ok_or_else(|| PyKeyError::new_err(name.to_owned()))?;
+        to_python(py, &value, &mut Vec::new())
+    }
+
+    fn __setitem__(&mut self, name: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let value = from_python(value, &mut Vec::new())?;
+        self.interpreter.define_global(name, value);
+        Ok(())
+    }
+}
+
+/// Runs Lox source in a fresh interpreter, returning the code passed to `exit()`, or 0.
+#[pyfunction]
+fn run(source: &str) -> PyResult<i32> {
+    PyInterpreter::new().run(source)
+}
+
+#[pymodule]
+fn rox(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyInterpreter>()?;
+    m.add_function(wrap_pyfunction!(run, m)?)?;
+    m.add("CompileError", m.py().get_type_bound::<CompileError>())?;
+    m.add("RuntimeError", m.py().get_type_bound::<RuntimeError>())?;
+    Ok(())
+}
+
+/// Converts a Lox value to Python. `maps` holds the maps being converted, to catch cycles.
+fn to_python(py: Python<'_>, value: &Value, maps: &mut Vec<*const ()>) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Nil => py.None(),
+        Value::Bool(b) => b.into_py(py),
+        Value::Number(n) => n.into_py(py),
+        Value::BigInt(n) => n.to_object(py),
+        Value::Decimal(n) => n.to_object(py),
+        Value::String_(s) => s.into_py(py),
+        Value::Map(map) => {
+            let address = map.as_ptr() as *const ();
+            if maps.contains(&address) {
+                return Err(PyTypeError::new_err(
+                    "Cannot convert a map that contains itself.",
+                ));
+            }
+            maps.push(address);
+            let dict = PyDict::new_bound(py);
+            for (key, item) in map.borrow().iter() {
+                dict.set_item(to_python(py, key, maps)?, to_python(py, item, maps)?)?;
+            }
+            maps.pop();
+            dict.into_py(py)
+        }
+        Value::NativeFunction(_) | Value::Namespace(_) | Value::Handle(_) => {
+            return Err(PyTypeError::new_err(format!(
+                "Cannot convert a {} to Python.",
+                value.type_name()
+            )))
+        }
+    })
+}
+
+/// Converts a Python object to a Lox value. `dicts` holds the dicts being converted.
+fn from_python(object: &Bound<'_, PyAny>, dicts: &mut Vec<*const ()>) -> PyResult<Value> {
+    if object.is_none() {
+        Ok(Value::Nil)
+    } else if let Ok(b) = object.downcast::<PyBool>() {
+        Ok(Value::Bool(b.is_true()))
+    } else if object.is_instance_of::<PyInt>() {
+        match object.extract::<i64>() {
+            Ok(n) if n.unsigned_abs() <= MAX_SAFE_INTEGER => Ok(Value::Number(n as f64)),
+            _ => Ok(Value::BigInt(object.extract::<BigInt>()?)),
+        }
+    } else if object.is_instance_of::<PyFloat>() {
+        Ok(Value::Number(object.extract()?))
+    } else if let Ok(s) = object.downcast::<PyString>() {
+        Ok(Value::String_(s.to_str()?.to_owned()))
+    } else if let Ok(dict) = object.downcast::<PyDict>() {
+        let address = dict.as_ptr() as *const ();
+        if dicts.contains(&address) {
+            return Err(PyTypeError::new_err(
+                "Cannot convert a dict that contains itself.",
+            ));
+        }
+        dicts.push(address);
+        let mut map = Map::new();
+        for (key, item) in dict {
+            let key = from_python(&key, dicts)?;
+            let item = from_python(&item, dicts)?;
+            map.insert(key, item)
+                .map_err(|error| PyTypeError::new_err(error.to_string()))?;
+        }
+        dicts.pop();
+        Ok(Value::map(map))
+    } else if is_decimal(object)? {
+        Ok(Value::Decimal(object.extract::<Decimal>()?))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "Cannot convert a {} to a Lox value.",
+            object.get_type().name()?
+        )))
+    }
+}
+
+fn is_decimal(object: &Bound<'_, PyAny>) -> PyResult<bool> {
+    let decimal = object.py().import_bound("decimal")?.getattr("Decimal")?;
+    object.is_instance(&decimal)
+}
+
+/// Writes to Python's `sys.stdout`, whatever it currently is.
+struct PythonStdout;
+
+impl PythonStdout {
+    fn call(method: &str, arguments: impl IntoPy<Py<PyTuple>>) -> io::Result<()> {
+        Python::with_gil(|py| {
+            py.import_bound("sys")?
+                .getattr("stdout")?
+                .call_method1(method, arguments)
+                .map(drop)
+        })
+        .map_err(io::Error::other)
+    }
+}
+
+impl Write for PythonStdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Self::call("write", (String::from_utf8_lossy(buf),))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Self::call("flush", ())
+    }
+}
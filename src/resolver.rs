@@ -0,0 +1,191 @@
+use crate::ast::{Expr, ExprVisitor, Stmt, StmtVisitor};
+use crate::diagnostic::Diagnostic;
+use crate::tokens::{Literal, Token};
+use std::collections::HashSet;
+
+/// Static checks run between parsing and interpretation.
+#[derive(Debug, Default)]
+pub struct Resolver {
+    scopes: Vec<HashSet<String>>,
+    /// How many loops enclose the statement being resolved.
+    loop_depth: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn resolve(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.execute(statement.clone());
+        }
+    }
+
+    /// Returns the errors reported so far, leaving none behind.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    fn error_at_token(&mut self, token: &Token, message: &str) {
+        self.diagnostics
+            .push(Diagnostic::error_at_token(token, message));
+    }
+
+    fn resolve_loop_body(&mut self, body: Stmt) {
+        self.loop_depth += 1;
+        self.execute(body);
+        self.loop_depth -= 1;
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashSet::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Globals may be redefined freely so the REPL stays forgiving, but a local scope
+    /// may only declare each name once.
+    fn declare(&mut self, name: &Token) {
+        let redeclared = self
+            .scopes
+            .last_mut()
+            .is_some_and(|scope| !scope.insert(name.lexeme.clone()));
+        if redeclared {
+            self.error_at_token(name, "Already a variable with this name in this scope.");
+        }
+    }
+}
+
+impl StmtVisitor<()> for Resolver {
+    fn visit_if_stmt(
+        &mut self,
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Box<Option<Stmt>>,
+    ) {
+        self.evaluate(condition);
+        self.execute(*then_branch);
+        if let Some(else_branch) = *else_branch {
+            self.execute(else_branch);
+        }
+    }
+
+    fn visit_block_stmt(&mut self, statements: Vec<Stmt>) {
+        self.begin_scope();
+        self.resolve(&statements);
+        self.end_scope();
+    }
+
+    fn visit_break_stmt(&mut self, keyword: Token) {
+        if self.loop_depth == 0 {
+            self.error_at_token(&keyword, "Can't use 'break' outside of a loop.");
+        }
+    }
+
+    fn visit_continue_stmt(&mut self, keyword: Token) {
+        if self.loop_depth == 0 {
+            self.error_at_token(&keyword, "Can't use 'continue' outside of a loop.");
+        }
+    }
+
+    fn visit_do_while_stmt(&mut self, body: Box<Stmt>, condition: Expr) {
+        self.resolve_loop_body(*body);
+        self.evaluate(condition);
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: Expr) {
+        self.evaluate(stmt);
+    }
+
+    fn visit_print_stmt(&mut self, stmt: Expr) {
+        self.evaluate(stmt);
+    }
+
+    fn visit_var_stmt(&mut self, name: Token, initializer: Option<Expr>) {
+        if let Some(initializer) = initializer {
+            self.evaluate(initializer);
+        }
+        self.declare(&name);
+    }
+
+    fn visit_while_stmt(&mut self, condition: Expr, body: Box<Stmt>, increment: Option<Expr>) {
+        self.evaluate(condition);
+        self.resolve_loop_body(*body);
+        if let Some(increment) = increment {
+            self.evaluate(increment);
+        }
+    }
+}
+
+impl ExprVisitor<()> for Resolver {
+    fn visit_assign_expr(&mut self, _name: Token, value: Box<Expr>) {
+        self.evaluate(*value);
+    }
+
+    fn visit_binary_expr(&mut self, left: Box<Expr>, _operator: Token, right: Box<Expr>) {
+        self.evaluate(*left);
+        self.evaluate(*right);
+    }
+
+    fn visit_call_expr(&mut self, callee: Box<Expr>, _paren: Token, arguments: Vec<Expr>) {
+        self.evaluate(*callee);
+        for argument in arguments {
+            self.evaluate(argument);
+        }
+    }
+
+    fn visit_get_expr(&mut self, object: Box<Expr>, _name: Token) {
+        self.evaluate(*object);
+    }
+
+    fn visit_global_expr(&mut self, _name: Token) {}
+
+    fn visit_grouping_expr(&mut self, expr: Box<Expr>) {
+        self.evaluate(*expr);
+    }
+
+    fn visit_index_expr(&mut self, object: Box<Expr>, _bracket: Token, index: Box<Expr>) {
+        self.evaluate(*object);
+        self.evaluate(*index);
+    }
+
+    fn visit_literal_expr(&mut self, _literal: Literal) {}
+
+    fn visit_logical_expr(&mut self, left: Box<Expr>, _operator: Token, right: Box<Expr>) {
+        self.evaluate(*left);
+        self.evaluate(*right);
+    }
+
+    fn visit_map_expr(&mut self, _brace: Token, entries: Vec<(Expr, Expr)>) {
+        for (key, value) in entries {
+            self.evaluate(key);
+            self.evaluate(value);
+        }
+    }
+
+    fn visit_set_global_expr(&mut self, _name: Token, value: Box<Expr>) {
+        self.evaluate(*value);
+    }
+
+    fn visit_set_index_expr(
+        &mut self,
+        object: Box<Expr>,
+        _bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    ) {
+        self.evaluate(*object);
+        self.evaluate(*index);
+        self.evaluate(*value);
+    }
+
+    fn visit_unary_expr(&mut self, _operator: Token, right: Box<Expr>) {
+        self.evaluate(*right);
+    }
+
+    fn visit_variable_expr(&self, _name: Token) {}
+}
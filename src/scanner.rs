@@ -1,37 +1,44 @@
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
 use crate::tokens::TokenType::{
-    And, Bang, BangEqual, Class, Comma, Dot, Else, Eof, Equal, EqualEqual, False, For, Fun,
-    Greater, GreaterEqual, Identifier, If, LeftBrace, LeftParen, Less, LessEqual, Minus, Nil,
-    Number, Or, Plus, Print, Return, RightBrace, RightParen, Semicolon, Slash, Star, String_,
+    And, Bang, BangEqual, Break, Class, Colon, Comma, Continue, Do, Dot, Else, Eof, Equal,
+    EqualEqual, False, For, Fun, Greater, GreaterEqual, GreaterGreater, Identifier, If, In,
+    LeftBrace, LeftBracket, LeftParen, Less, LessEqual, LessLess, Loop, Minus, Nil, Number, Or,
+    Plus, Print, Return, RightBrace, RightBracket, RightParen, Semicolon, Slash, Star, String_,
     Super, This, True, Var, While,
 };
-use crate::tokens::{Literal, Token, TokenType};
-use lazy_static::lazy_static;
+use crate::tokens::{Literal, Token, TokenType, Trivia};
+use num_bigint::BigInt;
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::str::FromStr;
+use std::sync::LazyLock;
 
-lazy_static! {
-    static ref KEYWORDS: HashMap<String, TokenType> = {
-        let mut m = HashMap::new();
-        m.insert("and".to_owned(), And);
-        m.insert("class".to_owned(), Class);
-        m.insert("else".to_owned(), Else);
-        m.insert("false".to_owned(), False);
-        m.insert("for".to_owned(), For);
-        m.insert("fun".to_owned(), Fun);
-        m.insert("if".to_owned(), If);
-        m.insert("nil".to_owned(), Nil);
-        m.insert("or".to_owned(), Or);
-        m.insert("print".to_owned(), Print);
-        m.insert("return".to_owned(), Return);
-        m.insert("super".to_owned(), Super);
-        m.insert("this".to_owned(), This);
-        m.insert("true".to_owned(), True);
-        m.insert("var".to_owned(), Var);
-        m.insert("while".to_owned(), While);
-        m
-    };
-}
+static KEYWORDS: LazyLock<HashMap<String, TokenType>> = LazyLock::new(|| {
+    let mut m = HashMap::new();
+    m.insert("and".to_owned(), And);
+    m.insert("break".to_owned(), Break);
+    m.insert("class".to_owned(), Class);
+    m.insert("continue".to_owned(), Continue);
+    m.insert("do".to_owned(), Do);
+    m.insert("else".to_owned(), Else);
+    m.insert("false".to_owned(), False);
+    m.insert("for".to_owned(), For);
+    m.insert("fun".to_owned(), Fun);
+    m.insert("if".to_owned(), If);
+    m.insert("in".to_owned(), In);
+    m.insert("loop".to_owned(), Loop);
+    m.insert("nil".to_owned(), Nil);
+    m.insert("or".to_owned(), Or);
+    m.insert("print".to_owned(), Print);
+    m.insert("return".to_owned(), Return);
+    m.insert("super".to_owned(), Super);
+    m.insert("this".to_owned(), This);
+    m.insert("true".to_owned(), True);
+    m.insert("var".to_owned(), Var);
+    m.insert("while".to_owned(), While);
+    m
+});
 
 pub struct Scanner {
     source: String,
@@ -39,6 +46,9 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: NonZeroUsize,
+    keep_trivia: bool,
+    pending_trivia: Vec<Trivia>,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl Scanner {
@@ -48,20 +58,65 @@ impl Scanner {
             tokens: Vec::new(),
             start: 0,
             current: 0,
-            line: NonZeroUsize::new(1).unwrap(),
+            line: NonZeroUsize::MIN,
+            keep_trivia: false,
+            pending_trivia: Vec::new(),
+            diagnostics: Vec::new(),
         }
     }
 
+    /// Creates a scanner that attaches `//` comments to tokens as trivia rather than
+    /// discarding them, so tools can reproduce them.
+    pub const fn with_trivia(source: String) -> Self {
+        let mut scanner = Self::new(source);
+        scanner.keep_trivia = true;
+        scanner
+    }
+
     pub fn scan_tokens(&mut self) -> Vec<Token> {
         while !self.is_at_end() {
             self.start = self.current;
             self.scan_token();
         }
-        self.tokens.push(Token::new(Eof, "", None, self.line));
+        let mut eof = Token::new(
+            Eof,
+            "",
+            None,
+            self.line,
+            Span::new(self.current, self.current),
+        );
+        eof.leading_trivia = std::mem::take(&mut self.pending_trivia);
+        self.tokens.push(eof);
         self.tokens.clone()
     }
 
-    fn is_at_end(&self) -> bool {
+    /// Returns the errors reported so far, leaving none behind.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    fn error(&mut self, message: &str) {
+        let span = Span::new(self.start, self.current);
+        self.diagnostics
+            .push(Diagnostic::error(self.line, span, message));
+    }
+
+    /// Reports a run of characters that cannot start a token, such as `@@@`, as a single
+    /// error and skips past it.
+    fn unexpected_characters(&mut self) {
+        while !self.is_at_end() && !can_start_token(self.peek()) {
+            self.advance();
+        }
+        let run = &self.source[self.start..self.current];
+        let message = if run.chars().count() == 1 {
+            format!("Unexpected character '{run}'.")
+        } else {
+            format!("Unexpected characters '{run}'.")
+        };
+        self.error(&message);
+    }
+
+    const fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
     }
 
@@ -72,6 +127,9 @@ impl Scanner {
             ')' => self.add_token(RightParen),
             '{' => self.add_token(LeftBrace),
             '}' => self.add_token(RightBrace),
+            '[' => self.add_token(LeftBracket),
+            ']' => self.add_token(RightBracket),
+            ':' => self.add_token(Colon),
             ',' => self.add_token(Comma),
             '.' => self.add_token(Dot),
             '-' => self.add_token(Minus),
@@ -80,29 +138,40 @@ impl Scanner {
             '*' => self.add_token(Star),
             '!' => {
                 let type_ = if self.matches('=') { BangEqual } else { Bang };
-                self.add_token(type_)
+                self.add_token(type_);
             }
             '=' => {
                 let type_ = if self.matches('=') { EqualEqual } else { Equal };
-                self.add_token(type_)
+                self.add_token(type_);
             }
             '<' => {
-                let type_ = if self.matches('=') { LessEqual } else { Less };
-                self.add_token(type_)
+                let type_ = if self.matches('=') {
+                    LessEqual
+                } else if self.matches('<') {
+                    LessLess
+                } else {
+                    Less
+                };
+                self.add_token(type_);
             }
             '>' => {
                 let type_ = if self.matches('=') {
                     GreaterEqual
+                } else if self.matches('>') {
+                    GreaterGreater
                 } else {
                     Greater
                 };
-                self.add_token(type_)
+                self.add_token(type_);
             }
             '/' => {
                 if self.matches('/') {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    if self.keep_trivia {
+                        self.add_comment();
+                    }
                 } else {
                     self.add_token(Slash);
                 }
@@ -111,36 +180,33 @@ impl Scanner {
             '\n' => self.increment_line(),
             '"' => self.string(),
             _ => {
-                if c.is_digit(10) {
-                    self.number()
+                if c.is_ascii_digit() {
+                    self.number();
                 } else if is_alphanumeric(c) {
-                    self.identifier()
+                    self.identifier();
                 } else {
-                    crate::error(self.line, "Unexpected character")
+                    self.unexpected_characters();
                 }
             }
         }
     }
 
+    // `current` is a byte offset that only ever moves by whole characters, so slicing the
+    // source at `start` or `current` cannot split a multi-byte character.
     fn advance(&mut self) -> char {
-        self.current += 1;
-        self.source.chars().nth(self.current - 1).unwrap()
+        let c = self.peek();
+        if !self.is_at_end() {
+            self.current += c.len_utf8();
+        }
+        c
     }
 
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current).unwrap()
-        }
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if (self.current + 1) >= self.source.len() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current + 1).unwrap()
-        }
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     fn add_token(&mut self, type_: TokenType) {
@@ -148,21 +214,36 @@ impl Scanner {
     }
 
     fn add_full_token(&mut self, type_: TokenType, literal: Option<Literal>) {
-        let token = Token::new(
+        let mut token = Token::new(
             type_,
             &self.source[self.start..self.current],
             literal,
             self.line,
+            Span::new(self.start, self.current),
         );
+        token.leading_trivia = std::mem::take(&mut self.pending_trivia);
         self.tokens.push(token);
     }
 
+    fn add_comment(&mut self) {
+        let trivia = Trivia {
+            text: self.source[self.start..self.current].to_owned(),
+            span: Span::new(self.start, self.current),
+        };
+        match self.tokens.last_mut() {
+            Some(previous) if previous.line == self.line && self.pending_trivia.is_empty() => {
+                previous.trailing_trivia.push(trivia);
+            }
+            _ => self.pending_trivia.push(trivia),
+        }
+    }
+
     fn matches(&mut self, expected: char) -> bool {
         if self.is_at_end() {
-            return true;
+            return false;
         }
-        if expected == self.source.chars().nth(self.current).unwrap() {
-            self.current += 1;
+        if expected == self.peek() {
+            self.current += expected.len_utf8();
             true
         } else {
             false
@@ -177,7 +258,8 @@ impl Scanner {
             self.advance();
         }
         if self.is_at_end() {
-            crate::error(self.line, "Unterminated string.");
+            self.error("Unterminated string.");
+            return;
         }
         self.advance();
         let literal =
@@ -186,18 +268,24 @@ impl Scanner {
     }
 
     fn number(&mut self) {
-        while self.peek().is_digit(10) {
+        while self.peek().is_ascii_digit() {
             self.advance();
         }
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             self.advance();
-            while self.peek().is_digit(10) {
+            while self.peek().is_ascii_digit() {
                 self.advance();
             }
+        } else if self.peek() == 'n' && !is_alphanumeric(self.peek_next()) {
+            // An `n` suffix makes an integer literal a bigint, as in `12345678901234567890n`.
+            let digits = &self.source[self.start..self.current];
+            let literal = Literal::BigInt(BigInt::from_str(digits).unwrap());
+            self.advance();
+            self.add_full_token(Number, Some(literal));
+            return;
         }
-        let literal = Literal::Number(
-            f64::from_str(&self.source[(self.start + 1)..(self.current - 1)]).unwrap(),
-        );
+        let literal =
+            Literal::Number(f64::from_str(&self.source[self.start..self.current]).unwrap());
         self.add_full_token(Number, Some(literal));
     }
 
@@ -205,18 +293,44 @@ impl Scanner {
         while is_alphanumeric(self.peek()) {
             self.advance();
         }
-        let text = &self.source[(self.start + 1)..(self.current - 1)];
+        let text = &self.source[self.start..self.current];
         let type_ = KEYWORDS
             .get(text)
             .map_or_else(|| Identifier, std::clone::Clone::clone);
         self.add_token(type_);
     }
 
-    fn increment_line(&mut self) {
-        self.line = NonZeroUsize::new(self.line.get() + 1).unwrap();
+    const fn increment_line(&mut self) {
+        self.line = self.line.saturating_add(1);
     }
 }
 
+const fn can_start_token(c: char) -> bool {
+    is_alphanumeric(c)
+        || c.is_ascii_whitespace()
+        || matches!(
+            c,
+            '(' | ')'
+                | '{'
+                | '}'
+                | '['
+                | ']'
+                | ':'
+                | ','
+                | '.'
+                | '-'
+                | '+'
+                | ';'
+                | '*'
+                | '!'
+                | '='
+                | '<'
+                | '>'
+                | '/'
+                | '"'
+        )
+}
+
 const fn is_alphanumeric(c: char) -> bool {
     c.is_ascii_alphanumeric() || c == '_'
 }
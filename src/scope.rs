@@ -0,0 +1,31 @@
+use crate::value::Value;
+use std::collections::BTreeMap;
+
+/// Variables a host hands to a script and reads back once it has run, as config scripts
+/// need. Pass it to `Interpreter::run_with_scope`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scope {
+    values: BTreeMap<String, Value>,
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a variable, replacing any earlier value of that name.
+    pub fn set(&mut self, name: &str, value: impl Into<Value>) {
+        self.values.insert(name.to_owned(), value.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    /// Every variable, in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+}
@@ -0,0 +1,41 @@
+use super::encoding::hex;
+use crate::interpreter::Interpreter;
+use crate::native::{string_argument, Namespace, NativeFunction};
+use crate::value::Value;
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+/// `Crypto` hashes the UTF-8 bytes of strings, returning digests as lowercase hex. MD5 is
+/// only fit for checksums, not for anything an attacker might forge.
+pub fn namespace() -> Namespace {
+    Namespace::new(
+        "Crypto",
+        vec![
+            NativeFunction::new("sha256", Some(1), sha256),
+            NativeFunction::new("md5", Some(1), md5),
+            NativeFunction::new("hmac", Some(2), hmac),
+        ],
+    )
+}
+
+fn sha256(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let text = string_argument("Crypto.sha256", &arguments[0])?;
+    Ok(Value::String_(hex(&Sha256::digest(text))))
+}
+
+fn md5(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let text = string_argument("Crypto.md5", &arguments[0])?;
+    Ok(Value::String_(hex(&Md5::digest(text))))
+}
+
+/// `Crypto.hmac(key, message)` returns the HMAC-SHA256 of `message` keyed with `key`.
+fn hmac(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let key = string_argument("Crypto.hmac", &arguments[0])?;
+    let message = string_argument("Crypto.hmac", &arguments[1])?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())
+        .map_err(|e| anyhow!("Crypto.hmac() could not use the key: {e}."))?;
+    mac.update(message.as_bytes());
+    Ok(Value::String_(hex(&mac.finalize().into_bytes())))
+}
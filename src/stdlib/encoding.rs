@@ -0,0 +1,112 @@
+use crate::interpreter::Interpreter;
+use crate::native::{string_argument, Namespace, NativeFunction};
+use crate::value::Value;
+use anyhow::Result;
+use std::fmt::Write;
+
+/// The standard base64 alphabet from RFC 4648.
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// `Encoding` turns the UTF-8 bytes of strings into base64 or hex text and back. Decoding
+/// fails if the bytes are not UTF-8 text, since strings are all rox can hold.
+pub fn namespace() -> Namespace {
+    Namespace::new(
+        "Encoding",
+        vec![
+            NativeFunction::new("base64Encode", Some(1), base64_encode),
+            NativeFunction::new("base64Decode", Some(1), base64_decode),
+            NativeFunction::new("hexEncode", Some(1), hex_encode),
+            NativeFunction::new("hexDecode", Some(1), hex_decode),
+        ],
+    )
+}
+
+/// Spells out bytes as lowercase hex, two digits each.
+pub(super) fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+fn text(function: &str, bytes: Vec<u8>) -> Result<Value> {
+    String::from_utf8(bytes)
+        .map(Value::String_)
+        .map_err(|_| anyhow!("{function}() decoded bytes that are not UTF-8 text."))
+}
+
+/// `Encoding.base64Encode(text)` returns padded base64.
+fn base64_encode(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let bytes = string_argument("Encoding.base64Encode", &arguments[0])?.as_bytes();
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(BASE64[(group >> (18 - 6 * i) & 63) as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    Ok(Value::String_(encoded))
+}
+
+/// `Encoding.base64Decode(text)` takes base64 with or without its padding.
+fn base64_decode(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let encoded = string_argument("Encoding.base64Decode", &arguments[0])?;
+    let invalid = || anyhow!("Encoding.base64Decode() expects base64, got '{encoded}'.");
+    let unpadded = encoded
+        .strip_suffix("==")
+        .or_else(|| encoded.strip_suffix('='));
+    if unpadded.is_some() && encoded.len() % 4 != 0 {
+        return Err(invalid());
+    }
+    let digits = unpadded.unwrap_or(encoded).as_bytes();
+    if digits.len() % 4 == 1 {
+        return Err(invalid());
+    }
+    let mut bytes = Vec::with_capacity(digits.len() / 4 * 3 + 2);
+    for chunk in digits.chunks(4) {
+        let mut group = 0;
+        for (i, &digit) in chunk.iter().enumerate() {
+            group |= base64_digit(digit).ok_or_else(invalid)? << (18 - 6 * i);
+        }
+        bytes.extend_from_slice(&group.to_be_bytes()[1..chunk.len()]);
+    }
+    text("Encoding.base64Decode", bytes)
+}
+
+fn base64_digit(digit: u8) -> Option<u32> {
+    let value = match digit {
+        b'A'..=b'Z' => digit - b'A',
+        b'a'..=b'z' => digit - b'a' + 26,
+        b'0'..=b'9' => digit - b'0' + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => return None,
+    };
+    Some(u32::from(value))
+}
+
+fn hex_encode(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let text = string_argument("Encoding.hexEncode", &arguments[0])?;
+    Ok(Value::String_(hex(text.as_bytes())))
+}
+
+/// `Encoding.hexDecode(text)` takes two hex digits per byte, in either case.
+fn hex_decode(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let encoded = string_argument("Encoding.hexDecode", &arguments[0])?;
+    let invalid = || anyhow!("Encoding.hexDecode() expects hex, got '{encoded}'.");
+    if encoded.len() % 2 != 0 || !encoded.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let bytes = (0..encoded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&encoded[i..i + 2], 16).map_err(|_| invalid()))
+        .collect::<Result<_>>()?;
+    text("Encoding.hexDecode", bytes)
+}
@@ -0,0 +1,215 @@
+use crate::capability::Capability;
+use crate::interpreter::Interpreter;
+use crate::map::Map;
+use crate::native::{string_argument, Namespace, NativeFunction};
+use crate::value::Value;
+use anyhow::Result;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+pub fn namespace() -> Namespace {
+    Namespace::new(
+        "Http",
+        vec![
+            NativeFunction::new("get", Some(1), get),
+            NativeFunction::new("post", None, post),
+        ],
+    )
+}
+
+/// `Http.get(url)` fetches `url` and returns a map holding the response `status`, `headers`
+/// and `body`.
+fn get(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Net, "Http.get")?;
+    let url = string_argument("Http.get", &arguments[0])?;
+    request("Http.get", "GET", url, None, &Map::new())
+}
+
+/// `Http.post(url, body, headers)` sends `body` to `url`, with `headers` (a map of strings)
+/// if given, and returns the response like `Http.get`.
+fn post(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Net, "Http.post")?;
+    let (url, body, headers) = match arguments {
+        [url, body] => (url, body, Map::new()),
+        [url, body, Value::Map(headers)] => (url, body, headers.borrow().clone()),
+        [_, _, other] => {
+            return Err(anyhow!(
+                "Http.post() expects a map of headers, got {}.",
+                other.type_name()
+            ))
+        }
+        _ => {
+            return Err(anyhow!(
+                "Http.post() expects 2 or 3 arguments but got {}.",
+                arguments.len()
+            ))
+        }
+    };
+    let url = string_argument("Http.post", url)?;
+    let body = string_argument("Http.post", body)?;
+    request("Http.post", "POST", url, Some(body), &headers)
+}
+
+/// Headers `request` always writes, which a script may not send a second copy of.
+const RESERVED_HEADERS: [&str; 3] = ["Host", "Connection", "Content-Length"];
+
+/// Sends a plain HTTP/1.1 request and reads the response until the server closes the
+/// connection. There is no TLS support, so only `http://` URLs are accepted. Fields that
+/// could end a line early or split the request line are refused, so a script cannot smuggle
+/// in headers or a second request.
+fn request(
+    function: &str,
+    method: &str,
+    url: &str,
+    body: Option<&str>,
+    headers: &Map,
+) -> Result<Value> {
+    let (host, port, path) = parse_url(function, url)?;
+    check_field(function, "method", method, char::is_whitespace)?;
+    check_field(function, "host", &host, char::is_whitespace)?;
+    check_field(function, "path", &path, char::is_whitespace)?;
+    let mut message = Vec::new();
+    write!(message, "{method} {path} HTTP/1.1\r\nHost: {host}")?;
+    if port != 80 {
+        write!(message, ":{port}")?;
+    }
+    write!(message, "\r\nConnection: close\r\n")?;
+    for (name, value) in headers.iter() {
+        let name = string_argument(function, name)?;
+        let value = string_argument(function, value)?;
+        check_field(function, "header name", name, |c| {
+            c == ':' || c.is_whitespace()
+        })?;
+        if name.is_empty() {
+            return Err(anyhow!("{function}() header names cannot be empty."));
+        }
+        if RESERVED_HEADERS
+            .iter()
+            .any(|reserved| name.eq_ignore_ascii_case(reserved))
+        {
+            return Err(anyhow!("{function}() sets the {name} header itself."));
+        }
+        check_field(function, "header value", value, |_| false)?;
+        write!(message, "{name}: {value}\r\n")?;
+    }
+    if let Some(body) = body {
+        write!(message, "Content-Length: {}\r\n", body.len())?;
+    }
+    write!(message, "\r\n{}", body.unwrap_or(""))?;
+
+    let mut stream = TcpStream::connect((host.as_str(), port))
+        .map_err(|e| anyhow!("{function}() could not connect to {host}:{port}: {e}."))?;
+    stream.write_all(&message)?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    parse_response(function, &response)
+}
+
+/// Fails if `text` has a control character other than tab, such as CR or LF, or a character
+/// `forbidden` rejects.
+fn check_field(
+    function: &str,
+    field: &str,
+    text: &str,
+    forbidden: impl Fn(char) -> bool,
+) -> Result<()> {
+    if text
+        .chars()
+        .any(|c| (c.is_control() && c != '\t') || forbidden(c))
+    {
+        return Err(anyhow!(
+            "{function}() cannot send {text:?} as a {field}: it has a character not allowed there."
+        ));
+    }
+    Ok(())
+}
+
+fn parse_url(function: &str, url: &str) -> Result<(String, u16, String)> {
+    if url.starts_with("https://") {
+        return Err(anyhow!("{function}() does not support https URLs."));
+    }
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err(anyhow!("{function}() expects an http:// URL, got {url}."));
+    };
+    let (authority, path) = rest.find('/').map_or((rest, "/"), |i| rest.split_at(i));
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .map_err(|_| anyhow!("{function}() URL has an invalid port: {url}."))?,
+        ),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err(anyhow!("{function}() URL has no host: {url}."));
+    }
+    Ok((host.to_owned(), port, path.to_owned()))
+}
+
+fn parse_response(function: &str, response: &[u8]) -> Result<Value> {
+    let malformed = || anyhow!("{function}() received a malformed response.");
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(malformed)?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let mut body = response[split + 4..].to_vec();
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(malformed)?;
+
+    let mut headers = Map::new();
+    let mut chunked = false;
+    for line in lines {
+        let (name, value) = line.split_once(':').ok_or_else(malformed)?;
+        let name = name.trim().to_ascii_lowercase();
+        let value = value.trim();
+        if name == "transfer-encoding" && value.eq_ignore_ascii_case("chunked") {
+            chunked = true;
+        }
+        // Repeated headers are joined into one value. Set-Cookie values can hold commas, so
+        // they go on separate lines instead.
+        let separator = if name == "set-cookie" { "\n" } else { ", " };
+        let key = Value::String_(name);
+        let value = match headers.get(&key)? {
+            Some(Value::String_(previous)) => format!("{previous}{separator}{value}"),
+            _ => value.to_owned(),
+        };
+        headers.insert(key, Value::String_(value))?;
+    }
+    if chunked {
+        body = dechunk(&body).ok_or_else(malformed)?;
+    }
+
+    let mut result = Map::new();
+    result.insert(
+        Value::String_("status".to_owned()),
+        Value::Number(status.into()),
+    )?;
+    result.insert(Value::String_("headers".to_owned()), Value::map(headers))?;
+    result.insert(
+        Value::String_("body".to_owned()),
+        Value::String_(String::from_utf8_lossy(&body).into_owned()),
+    )?;
+    Ok(Value::map(result))
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body.
+fn dechunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|window| window == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_end]).ok()?;
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
@@ -0,0 +1,54 @@
+use crate::interpreter::Interpreter;
+use crate::log::{self, Level};
+use crate::native::{string_argument, Namespace, NativeFunction};
+use crate::value::Value;
+use anyhow::Result;
+
+/// `Log` writes timestamped messages to standard error, skipping those below the level set
+/// with `Log.setLevel` or `--log-level`, which starts at info.
+pub fn namespace() -> Namespace {
+    Namespace::new(
+        "Log",
+        vec![
+            NativeFunction::new("debug", Some(1), debug),
+            NativeFunction::new("info", Some(1), info),
+            NativeFunction::new("warn", Some(1), warn),
+            NativeFunction::new("error", Some(1), error),
+            NativeFunction::new("setLevel", Some(1), set_level),
+        ],
+    )
+}
+
+fn debug(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    message(interpreter, Level::Debug, &arguments[0])
+}
+
+fn info(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    message(interpreter, Level::Info, &arguments[0])
+}
+
+fn warn(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    message(interpreter, Level::Warn, &arguments[0])
+}
+
+fn error(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    message(interpreter, Level::Error, &arguments[0])
+}
+
+/// Logs any value, printed as `print` would print it.
+#[allow(clippy::unnecessary_wraps)] // Every native shares the `NativeFn` signature.
+fn message(interpreter: &Interpreter, level: Level, value: &Value) -> Result<Value> {
+    if level >= interpreter.log_level() {
+        log::write(level, &value.to_string());
+    }
+    Ok(Value::Nil)
+}
+
+/// `Log.setLevel(level)` takes `"debug"`, `"info"`, `"warn"` or `"error"`.
+fn set_level(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let level = string_argument("Log.setLevel", &arguments[0])?
+        .parse()
+        .map_err(|e| anyhow!("Log.setLevel() {e}."))?;
+    interpreter.set_log_level(level);
+    Ok(Value::Nil)
+}
@@ -0,0 +1,32 @@
+mod crypto;
+mod encoding;
+mod http;
+mod log;
+mod net;
+mod number;
+mod os;
+mod path;
+mod process;
+mod stdin;
+mod string_builder;
+mod test;
+
+use crate::native::Namespace;
+
+/// The namespaces every interpreter starts with.
+pub fn namespaces() -> Vec<Namespace> {
+    vec![
+        crypto::namespace(),
+        encoding::namespace(),
+        http::namespace(),
+        log::namespace(),
+        net::namespace(),
+        number::namespace(),
+        os::namespace(),
+        path::namespace(),
+        process::namespace(),
+        stdin::namespace(),
+        string_builder::namespace(),
+        test::namespace(),
+    ]
+}
@@ -0,0 +1,95 @@
+use crate::capability::Capability;
+use crate::interpreter::Interpreter;
+use crate::native::{
+    handle_argument, integer_argument, string_argument, Handle, Namespace, NativeFunction,
+};
+use crate::value::Value;
+use anyhow::Result;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::rc::Rc;
+
+const READ_SIZE: usize = 4096;
+
+pub fn namespace() -> Namespace {
+    Namespace::new(
+        "Net",
+        vec![
+            NativeFunction::new("connect", Some(2), connect),
+            NativeFunction::new("listen", Some(2), listen),
+            NativeFunction::new("accept", Some(1), accept),
+            NativeFunction::new("read", Some(1), read),
+            NativeFunction::new("write", Some(2), write),
+            NativeFunction::new("close", Some(1), close),
+        ],
+    )
+}
+
+fn address(function: &str, arguments: &[Value]) -> Result<(String, u16)> {
+    let host = string_argument(function, &arguments[0])?;
+    let port = integer_argument(function, &arguments[1])?;
+    let port =
+        u16::try_from(port).map_err(|_| anyhow!("{function}() port {port} is out of range."))?;
+    Ok((host.to_owned(), port))
+}
+
+/// `Net.connect(host, port)` opens a TCP connection and returns a socket.
+fn connect(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Net, "Net.connect")?;
+    let (host, port) = address("Net.connect", arguments)?;
+    let stream = TcpStream::connect((host.as_str(), port))
+        .map_err(|e| anyhow!("Net.connect() could not connect to {host}:{port}: {e}."))?;
+    Ok(Value::Handle(Rc::new(Handle::new("socket", stream))))
+}
+
+/// `Net.listen(host, port)` binds a TCP listener.
+fn listen(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Net, "Net.listen")?;
+    let (host, port) = address("Net.listen", arguments)?;
+    let listener = TcpListener::bind((host.as_str(), port))
+        .map_err(|e| anyhow!("Net.listen() could not listen on {host}:{port}: {e}."))?;
+    Ok(Value::Handle(Rc::new(Handle::new("listener", listener))))
+}
+
+/// `Net.accept(listener)` waits for the next incoming connection and returns its socket.
+fn accept(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Net, "Net.accept")?;
+    let listener = handle_argument("Net.accept", &arguments[0])?;
+    let stream = listener.with("Net.accept", |listener: &mut TcpListener| {
+        let (stream, _) = listener.accept()?;
+        Ok(stream)
+    })?;
+    Ok(Value::Handle(Rc::new(Handle::new("socket", stream))))
+}
+
+/// `Net.read(socket)` returns the next chunk of received text, or nil once the peer has
+/// closed the connection.
+fn read(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let socket = handle_argument("Net.read", &arguments[0])?;
+    socket.with("Net.read", |stream: &mut TcpStream| {
+        let mut buffer = [0; READ_SIZE];
+        let count = stream.read(&mut buffer)?;
+        Ok(if count == 0 {
+            Value::Nil
+        } else {
+            Value::String_(String::from_utf8_lossy(&buffer[..count]).into_owned())
+        })
+    })
+}
+
+/// `Net.write(socket, text)` sends all of `text`.
+fn write(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let socket = handle_argument("Net.write", &arguments[0])?;
+    let text = string_argument("Net.write", &arguments[1])?;
+    socket.with("Net.write", |stream: &mut TcpStream| {
+        stream.write_all(text.as_bytes())?;
+        Ok(Value::Nil)
+    })
+}
+
+/// `Net.close(handle)` closes a socket or listener. Closing twice is harmless.
+fn close(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    handle_argument("Net.close", &arguments[0])?.close();
+    Ok(Value::Nil)
+}
@@ -0,0 +1,10 @@
+use crate::native::Namespace;
+use crate::value::Value;
+
+/// `Number` holds the special floating-point values, which have no literal syntax.
+pub fn namespace() -> Namespace {
+    Namespace::new("Number", Vec::new())
+        .constant("NAN", Value::Number(f64::NAN))
+        .constant("INFINITY", Value::Number(f64::INFINITY))
+        .constant("EPSILON", Value::Number(f64::EPSILON))
+}
@@ -0,0 +1,84 @@
+use crate::capability::Capability;
+use crate::interpreter::Interpreter;
+use crate::map::Map;
+use crate::native::{string_argument, Namespace, NativeFunction};
+use crate::value::Value;
+use anyhow::Result;
+use std::env;
+use std::fs;
+
+/// `Os` tells a script about the machine it runs on. The working directory belongs to the
+/// whole process, so `Os.chdir` moves any host embedding the interpreter too.
+pub fn namespace() -> Namespace {
+    Namespace::new(
+        "Os",
+        vec![
+            NativeFunction::new("platform", Some(0), platform),
+            NativeFunction::new("cwd", Some(0), cwd),
+            NativeFunction::new("chdir", Some(1), chdir),
+            NativeFunction::new("listDir", Some(1), list_dir),
+            NativeFunction::new("hostname", Some(0), hostname),
+        ],
+    )
+}
+
+/// `Os.platform()` returns the operating system rox was built for, such as `"linux"`,
+/// `"macos"` or `"windows"`.
+#[allow(clippy::unnecessary_wraps)] // Every native shares the `NativeFn` signature.
+fn platform(_interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value> {
+    Ok(Value::String_(env::consts::OS.to_owned()))
+}
+
+fn cwd(interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Files, "Os.cwd")?;
+    let directory =
+        env::current_dir().map_err(|e| anyhow!("Os.cwd() could not find the directory: {e}."))?;
+    Ok(Value::String_(directory.to_string_lossy().into_owned()))
+}
+
+/// `Os.chdir(path)` makes `path` the working directory, which relative paths then start from.
+fn chdir(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Files, "Os.chdir")?;
+    let path = string_argument("Os.chdir", &arguments[0])?;
+    env::set_current_dir(path).map_err(|e| anyhow!("Os.chdir() could not enter {path}: {e}."))?;
+    Ok(Value::Nil)
+}
+
+/// `Os.listDir(path)` returns a map from the name of each entry in the directory to what it
+/// is: `"file"`, `"dir"`, `"symlink"` (which is not followed), or `"other"`, such as a
+/// socket. The names are sorted.
+fn list_dir(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Files, "Os.listDir")?;
+    let path = string_argument("Os.listDir", &arguments[0])?;
+    let unreadable = |e| anyhow!("Os.listDir() could not read {path}: {e}.");
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(path).map_err(unreadable)? {
+        let entry = entry.map_err(unreadable)?;
+        let file_type = entry.file_type().map_err(unreadable)?;
+        let kind = if file_type.is_symlink() {
+            "symlink"
+        } else if file_type.is_dir() {
+            "dir"
+        } else if file_type.is_file() {
+            "file"
+        } else {
+            "other"
+        };
+        entries.push((entry.file_name().to_string_lossy().into_owned(), kind));
+    }
+    entries.sort_unstable();
+    let mut listing = Map::new();
+    for (name, kind) in entries {
+        listing.insert(Value::String_(name), Value::String_(kind.to_owned()))?;
+    }
+    Ok(Value::map(listing))
+}
+
+/// `Os.hostname()` returns the machine's name, which identifies it on the network, so it
+/// needs the same capability as connecting to one.
+fn hostname(interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Net, "Os.hostname")?;
+    Ok(Value::String_(
+        gethostname::gethostname().to_string_lossy().into_owned(),
+    ))
+}
@@ -0,0 +1,86 @@
+use crate::capability::Capability;
+use crate::interpreter::Interpreter;
+use crate::native::{string_argument, Namespace, NativeFunction};
+use crate::value::Value;
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `Path` takes file paths apart and puts them together with the host's separators. Only
+/// `canonicalize` looks at the file system; the rest work on the text alone.
+pub fn namespace() -> Namespace {
+    Namespace::new(
+        "Path",
+        vec![
+            NativeFunction::new("join", None, join),
+            NativeFunction::new("basename", Some(1), basename),
+            NativeFunction::new("dirname", Some(1), dirname),
+            NativeFunction::new("extension", Some(1), extension),
+            NativeFunction::new("isAbsolute", Some(1), is_absolute),
+            NativeFunction::new("canonicalize", Some(1), canonicalize),
+        ],
+    )
+}
+
+fn path_value(path: &Path) -> Value {
+    Value::String_(path.to_string_lossy().into_owned())
+}
+
+/// `Path.join(path, more...)` adds each path to the one before it. An absolute path
+/// replaces everything before it.
+fn join(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    if arguments.is_empty() {
+        return Err(anyhow!("Path.join() expects at least one path."));
+    }
+    let mut path = PathBuf::new();
+    for argument in arguments {
+        path.push(string_argument("Path.join", argument)?);
+    }
+    Ok(path_value(&path))
+}
+
+/// `Path.basename(path)` returns the last component, or nil if the path ends in `..` or
+/// has none, as `/` does.
+fn basename(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let path = Path::new(string_argument("Path.basename", &arguments[0])?);
+    Ok(path
+        .file_name()
+        .map_or(Value::Nil, |name| path_value(Path::new(name))))
+}
+
+/// `Path.dirname(path)` returns everything before the last component, `.` for a lone
+/// relative name, or nil if the path is a root or empty.
+fn dirname(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let path = Path::new(string_argument("Path.dirname", &arguments[0])?);
+    Ok(path.parent().map_or(Value::Nil, |parent| {
+        if parent.as_os_str().is_empty() {
+            Value::String_(".".to_owned())
+        } else {
+            path_value(parent)
+        }
+    }))
+}
+
+/// `Path.extension(path)` returns the last component's extension without its dot, or nil
+/// if it has none. A leading dot, as in `.bashrc`, does not start one.
+fn extension(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let path = Path::new(string_argument("Path.extension", &arguments[0])?);
+    Ok(path
+        .extension()
+        .map_or(Value::Nil, |extension| path_value(Path::new(extension))))
+}
+
+fn is_absolute(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let path = Path::new(string_argument("Path.isAbsolute", &arguments[0])?);
+    Ok(Value::Bool(path.is_absolute()))
+}
+
+/// `Path.canonicalize(path)` returns the absolute path with every `.`, `..` and symbolic
+/// link resolved. The path must exist.
+fn canonicalize(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Files, "Path.canonicalize")?;
+    let path = string_argument("Path.canonicalize", &arguments[0])?;
+    fs::canonicalize(path)
+        .map(|path| path_value(&path))
+        .map_err(|e| anyhow!("Path.canonicalize() could not resolve {path}: {e}."))
+}
@@ -0,0 +1,44 @@
+use crate::capability::Capability;
+use crate::interpreter::Interpreter;
+use crate::map::Map;
+use crate::native::{string_argument, Namespace, NativeFunction};
+use crate::value::Value;
+use anyhow::Result;
+use std::process::Command;
+
+pub fn namespace() -> Namespace {
+    Namespace::new("Process", vec![NativeFunction::new("run", None, run)])
+}
+
+/// `Process.run(cmd, arg...)` runs a program to completion and returns a map holding its
+/// `stdout`, `stderr` and exit `status` (nil if it was killed by a signal).
+fn run(interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    interpreter.require(Capability::Process, "Process.run")?;
+    let (program, args) = arguments
+        .split_first()
+        .ok_or_else(|| anyhow!("Process.run() expects a command."))?;
+    let mut command = Command::new(string_argument("Process.run", program)?);
+    for arg in args {
+        command.arg(string_argument("Process.run", arg)?);
+    }
+    let output = command
+        .output()
+        .map_err(|e| anyhow!("Process.run() could not run {program}: {e}."))?;
+    let mut result = Map::new();
+    result.insert(
+        Value::String_("stdout".to_owned()),
+        Value::String_(String::from_utf8_lossy(&output.stdout).into_owned()),
+    )?;
+    result.insert(
+        Value::String_("stderr".to_owned()),
+        Value::String_(String::from_utf8_lossy(&output.stderr).into_owned()),
+    )?;
+    result.insert(
+        Value::String_("status".to_owned()),
+        output
+            .status
+            .code()
+            .map_or(Value::Nil, |code| Value::Number(code.into())),
+    )?;
+    Ok(Value::map(result))
+}
@@ -0,0 +1,89 @@
+use crate::interpreter::Interpreter;
+use crate::native::{parse_decimal, Namespace, NativeFunction};
+use crate::value::Value;
+use anyhow::Result;
+use std::io::{self, BufRead, Read};
+
+/// `Stdin` reads standard input as it arrives, so `rox tool.lox < data.txt` can filter it.
+/// All three share the same buffer, so they can be mixed. Invalid UTF-8 is replaced.
+pub fn namespace() -> Namespace {
+    Namespace::new(
+        "Stdin",
+        vec![
+            NativeFunction::new("readAll", Some(0), read_all),
+            NativeFunction::new("readLine", Some(0), read_line),
+            NativeFunction::new("readNumber", Some(0), read_number),
+        ],
+    )
+}
+
+/// `Stdin.readAll()` returns the rest of the input, which is empty once it has all been read.
+fn read_all(_interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value> {
+    let mut bytes = Vec::new();
+    io::stdin()
+        .lock()
+        .read_to_end(&mut bytes)
+        .map_err(|e| anyhow!("Stdin.readAll() could not read: {e}."))?;
+    Ok(Value::String_(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// `Stdin.readLine()` returns the next line without its line ending, or nil at the end of
+/// the input.
+fn read_line(_interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value> {
+    let mut bytes = Vec::new();
+    let count = io::stdin()
+        .lock()
+        .read_until(b'\n', &mut bytes)
+        .map_err(|e| anyhow!("Stdin.readLine() could not read: {e}."))?;
+    if count == 0 {
+        return Ok(Value::Nil);
+    }
+    if bytes.ends_with(b"\n") {
+        bytes.pop();
+        if bytes.ends_with(b"\r") {
+            bytes.pop();
+        }
+    }
+    Ok(Value::String_(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// `Stdin.readNumber()` skips whitespace and reads the next word as a number, or returns
+/// nil at the end of the input. The rest of the line is left for the next read.
+fn read_number(_interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value> {
+    let mut stdin = io::stdin().lock();
+    let mut word = Vec::new();
+    loop {
+        let buffer = stdin
+            .fill_buf()
+            .map_err(|e| anyhow!("Stdin.readNumber() could not read: {e}."))?;
+        if buffer.is_empty() {
+            break;
+        }
+        let skipped = if word.is_empty() {
+            buffer
+                .iter()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count()
+        } else {
+            0
+        };
+        let taken = buffer[skipped..]
+            .iter()
+            .take_while(|b| !b.is_ascii_whitespace())
+            .count();
+        word.extend_from_slice(&buffer[skipped..skipped + taken]);
+        let done = skipped + taken < buffer.len();
+        stdin.consume(skipped + taken);
+        if done && !word.is_empty() {
+            break;
+        }
+    }
+    drop(stdin);
+    if word.is_empty() {
+        return Ok(Value::Nil);
+    }
+    let word = String::from_utf8_lossy(&word);
+    parse_decimal(&word)
+        .map(Value::Number)
+        .ok_or_else(|| anyhow!("Stdin.readNumber() expected a number, got '{word}'."))
+}
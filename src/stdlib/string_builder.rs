@@ -0,0 +1,46 @@
+use crate::interpreter::Interpreter;
+use crate::native::{handle_argument, Handle, Namespace, NativeFunction};
+use crate::value::Value;
+use anyhow::Result;
+use std::fmt::Write;
+use std::rc::Rc;
+
+/// `StringBuilder` builds a string piece by piece in place. `s = s + piece` in a loop copies
+/// `s` every time round, which is quadratic in its final length.
+pub fn namespace() -> Namespace {
+    Namespace::new(
+        "StringBuilder",
+        vec![
+            NativeFunction::new("new", Some(0), new),
+            NativeFunction::new("append", Some(2), append),
+            NativeFunction::new("toString", Some(1), to_string),
+        ],
+    )
+}
+
+/// `StringBuilder.new()` returns an empty string builder.
+#[allow(clippy::unnecessary_wraps)] // Every native shares the `NativeFn` signature.
+fn new(_interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value> {
+    Ok(Value::Handle(Rc::new(Handle::new(
+        "string builder",
+        String::new(),
+    ))))
+}
+
+/// `StringBuilder.append(builder, value)` adds `value` to the end, written as `print`
+/// would write it.
+fn append(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let builder = handle_argument("StringBuilder.append", &arguments[0])?;
+    builder.with("StringBuilder.append", |string: &mut String| {
+        write!(string, "{}", arguments[1])?;
+        Ok(Value::Nil)
+    })
+}
+
+/// `StringBuilder.toString(builder)` returns everything appended so far.
+fn to_string(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let builder = handle_argument("StringBuilder.toString", &arguments[0])?;
+    builder.with("StringBuilder.toString", |string: &mut String| {
+        Ok(Value::String_(string.clone()))
+    })
+}
@@ -0,0 +1,82 @@
+use crate::interpreter::Interpreter;
+use crate::native::{string_argument, Namespace, NativeFunction};
+use crate::value::Value;
+use anyhow::{Error, Result};
+
+/// `Test` holds assertions for scripts that check themselves. A failed one stops the script
+/// with a runtime error, so `rox` exits with status 70.
+pub fn namespace() -> Namespace {
+    Namespace::new(
+        "Test",
+        vec![
+            NativeFunction::new("expectEq", None, expect_eq),
+            NativeFunction::new("expectTrue", None, expect_true),
+        ],
+    )
+}
+
+/// `Test.expectEq(actual, expected[, message])` checks the values are equal, as `==` would.
+fn expect_eq(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let (actual, expected, message) = match arguments {
+        [actual, expected] => (actual, expected, None),
+        [actual, expected, message] => (actual, expected, Some(message)),
+        _ => return Err(arity_error("expectEq", 2, arguments)),
+    };
+    let message = message_argument("expectEq", message)?;
+    if Interpreter::is_equal(actual, expected) {
+        return Ok(Value::Nil);
+    }
+    Err(failure(
+        "expectEq",
+        message,
+        &format!("expected {}, got {}", quoted(expected), quoted(actual)),
+    ))
+}
+
+/// `Test.expectTrue(value[, message])` checks the value is truthy.
+fn expect_true(_interpreter: &mut Interpreter, arguments: &[Value]) -> Result<Value> {
+    let (value, message) = match arguments {
+        [value] => (value, None),
+        [value, message] => (value, Some(message)),
+        _ => return Err(arity_error("expectTrue", 1, arguments)),
+    };
+    let message = message_argument("expectTrue", message)?;
+    if Interpreter::is_truthy(value) {
+        return Ok(Value::Nil);
+    }
+    Err(failure(
+        "expectTrue",
+        message,
+        &format!("got {}", quoted(value)),
+    ))
+}
+
+fn arity_error(function: &str, arity: usize, arguments: &[Value]) -> Error {
+    anyhow!(
+        "Test.{function}() expects {arity} or {} arguments but got {}.",
+        arity + 1,
+        arguments.len()
+    )
+}
+
+fn message_argument<'a>(function: &str, message: Option<&'a Value>) -> Result<Option<&'a str>> {
+    message
+        .map(|message| string_argument(&format!("Test.{function}"), message))
+        .transpose()
+}
+
+/// Builds the error for a failed assertion, led by the script's message if it gave one.
+fn failure(function: &str, message: Option<&str>, detail: &str) -> Error {
+    message.map_or_else(
+        || anyhow!("Test.{function}() failed: {detail}."),
+        |message| anyhow!("{message}: {detail}."),
+    )
+}
+
+/// Shows strings quoted, so `"1"` and `1` can be told apart in a failure.
+fn quoted(value: &Value) -> String {
+    match value {
+        Value::String_(s) => format!("{s:?}"),
+        _ => value.to_string(),
+    }
+}
@@ -1,12 +1,17 @@
+use crate::span::Span;
+use num_bigint::BigInt;
 use std::fmt;
 use std::num::NonZeroUsize;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenType {
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -22,16 +27,23 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
     Identifier,
     String_,
     Number,
     And,
+    Break,
     Class,
+    Continue,
+    Do,
     Else,
     False,
     Fun,
     For,
     If,
+    In,
+    Loop,
     Nil,
     Or,
     Print,
@@ -50,6 +62,9 @@ pub struct Token {
     pub lexeme: String,
     pub literal: Option<Literal>,
     pub line: NonZeroUsize,
+    pub span: Span,
+    pub leading_trivia: Vec<Trivia>,
+    pub trailing_trivia: Vec<Trivia>,
 }
 
 impl Token {
@@ -58,12 +73,16 @@ impl Token {
         lexeme: &str,
         literal: Option<Literal>,
         line: NonZeroUsize,
+        span: Span,
     ) -> Self {
         Self {
             type_,
             lexeme: lexeme.to_owned(),
             literal,
             line,
+            span,
+            leading_trivia: Vec::new(),
+            trailing_trivia: Vec::new(),
         }
     }
 }
@@ -74,10 +93,21 @@ impl fmt::Display for Token {
     }
 }
 
+/// A comment the scanner kept instead of discarding.
+///
+/// Comments on their own lines lead the next token; a comment following a token on the
+/// same line trails it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub text: String,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
     String_(String),
     Number(f64),
+    BigInt(BigInt),
     Bool(bool),
     Nil,
 }
@@ -0,0 +1,246 @@
+//! Recording the results of natives whose results differ from run to run, and feeding them
+//! back, so a run can be reproduced exactly.
+//!
+//! Only natives that return plain values are recorded. `Net` hands out sockets, which
+//! cannot be replayed, and plugins' natives are their own business.
+
+use crate::interpreter::Interpreter;
+use crate::map::Map;
+use crate::value::Value;
+use anyhow::Result;
+use num_bigint::BigInt;
+use rust_decimal::Decimal;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// The natives whose results are recorded.
+pub const RECORDED: &[&str] = &[
+    "random",
+    "randomInt",
+    "Process.run",
+    "Http.get",
+    "Http.post",
+    "Stdin.readAll",
+    "Stdin.readLine",
+    "Stdin.readNumber",
+];
+
+/// The first bytes of every trace file.
+const MAGIC: &[u8] = b"ROX TRACE 1\n";
+
+/// How deeply maps in a trace may nest, so a corrupt one cannot overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+/// One call to a recorded native: its name and what it returned, or the message of the
+/// error it failed with.
+#[derive(Debug, Clone)]
+struct Entry {
+    native: &'static str,
+    result: Result<Value, String>,
+}
+
+/// The calls recorded so far. The interpreter's natives hold on to it, so it keeps growing
+/// for as long as the interpreter runs scripts.
+pub struct Recording {
+    entries: Rc<RefCell<Vec<Entry>>>,
+}
+
+impl Recording {
+    /// Starts recording every call `interpreter` makes to the natives in `RECORDED`.
+    pub fn start(interpreter: &mut Interpreter) -> Result<Self> {
+        let entries = Rc::new(RefCell::new(Vec::new()));
+        for &name in RECORDED {
+            if let Some(original) = interpreter.native(name) {
+                let entries = Rc::clone(&entries);
+                interpreter.mock(name, move |interpreter, arguments| {
+                    let result = original.call(interpreter, arguments);
+                    entries.borrow_mut().push(Entry {
+                        native: name,
+                        result: result
+                            .as_ref()
+                            .map(Clone::clone)
+                            .map_err(ToString::to_string),
+                    });
+                    result
+                })?;
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    /// Writes the calls recorded so far to `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut bytes = MAGIC.to_vec();
+        for entry in self.entries.borrow().iter() {
+            write_string(&mut bytes, entry.native);
+            match &entry.result {
+                Ok(value) => {
+                    bytes.push(0);
+                    write_value(&mut bytes, value)?;
+                }
+                Err(message) => {
+                    bytes.push(1);
+                    write_string(&mut bytes, message);
+                }
+            }
+        }
+        fs::write(path, bytes).map_err(|e| anyhow!("Cannot write {}: {}.", path.display(), e))
+    }
+}
+
+/// Makes the natives in `RECORDED` return, call by call, what they returned when the trace
+/// at `path` was recorded, without doing anything else. A call the trace does not have is
+/// a runtime error.
+pub fn replay(interpreter: &mut Interpreter, path: &Path) -> Result<()> {
+    let bytes = fs::read(path).map_err(|e| anyhow!("Cannot read {}: {}.", path.display(), e))?;
+    let entries = read_trace(&bytes)
+        .map_err(|e| anyhow!("{} is not a valid trace: {}", path.display(), e))?;
+    let entries = Rc::new(RefCell::new(entries));
+    for &name in RECORDED {
+        let entries = Rc::clone(&entries);
+        interpreter.mock(name, move |_interpreter, _arguments| {
+            let entry = entries
+                .borrow_mut()
+                .pop_front()
+                .ok_or_else(|| anyhow!("The trace ended before this call to {name}()."))?;
+            if entry.native != name {
+                return Err(anyhow!(
+                    "The trace has a call to {}() here, not {name}().",
+                    entry.native
+                ));
+            }
+            entry.result.map_err(|message| anyhow!("{message}"))
+        })?;
+    }
+    Ok(())
+}
+
+fn write_string(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend_from_slice(&(s.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+fn write_value(bytes: &mut Vec<u8>, value: &Value) -> Result<()> {
+    match value {
+        Value::Nil => bytes.push(0),
+        Value::Bool(b) => bytes.extend_from_slice(&[1, u8::from(*b)]),
+        Value::Number(n) => {
+            bytes.push(2);
+            bytes.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::String_(s) => {
+            bytes.push(3);
+            write_string(bytes, s);
+        }
+        Value::BigInt(n) => {
+            bytes.push(4);
+            write_string(bytes, &n.to_string());
+        }
+        Value::Decimal(n) => {
+            bytes.push(5);
+            write_string(bytes, &n.to_string());
+        }
+        Value::Map(map) => {
+            let map = map.borrow();
+            bytes.push(6);
+            bytes.extend_from_slice(&(map.iter().count() as u64).to_le_bytes());
+            for (key, item) in map.iter() {
+                write_value(bytes, key)?;
+                write_value(bytes, item)?;
+            }
+        }
+        Value::NativeFunction(_) | Value::Namespace(_) | Value::Handle(_) => {
+            return Err(anyhow!("Cannot record a {}.", value.type_name()))
+        }
+    }
+    Ok(())
+}
+
+fn read_trace(bytes: &[u8]) -> Result<VecDeque<Entry>> {
+    let mut reader = Reader(
+        bytes
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| anyhow!("it does not start with the trace header."))?,
+    );
+    let mut entries = VecDeque::new();
+    while !reader.0.is_empty() {
+        let name = reader.string()?;
+        let native = RECORDED
+            .iter()
+            .copied()
+            .find(|&recorded| recorded == name)
+            .ok_or_else(|| anyhow!("{name}() is not a recorded native."))?;
+        let result = match reader.byte()? {
+            0 => Ok(reader.value(0)?),
+            1 => Err(reader.string()?),
+            tag => return Err(anyhow!("unknown result tag {tag}.")),
+        };
+        entries.push_back(Entry { native, result });
+    }
+    Ok(entries)
+}
+
+/// The bytes of a trace still to be read.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        if self.0.len() < count {
+            return Err(anyhow!("it ends in the middle of a call."));
+        }
+        let (taken, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = usize::try_from(self.u64()?)?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| anyhow!("it holds a string that is not UTF-8."))
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value> {
+        if depth > MAX_DEPTH {
+            return Err(anyhow!("it holds maps nested too deeply."));
+        }
+        Ok(match self.byte()? {
+            0 => Value::Nil,
+            1 => Value::Bool(self.byte()? != 0),
+            2 => Value::Number(f64::from_bits(self.u64()?)),
+            3 => Value::String_(self.string()?),
+            4 => Value::BigInt(
+                BigInt::from_str(&self.string()?).map_err(|_| anyhow!("it holds a bad bigint."))?,
+            ),
+            5 => Value::Decimal(
+                Decimal::from_str(&self.string()?)
+                    .map_err(|_| anyhow!("it holds a bad decimal."))?,
+            ),
+            6 => {
+                let mut map = Map::new();
+                for _ in 0..self.u64()? {
+                    let key = self.value(depth + 1)?;
+                    let item = self.value(depth + 1)?;
+                    map.insert(key, item)?;
+                }
+                Value::map(map)
+            }
+            tag => return Err(anyhow!("unknown value tag {tag}.")),
+        })
+    }
+}
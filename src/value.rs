@@ -1,19 +1,72 @@
+use crate::map::Map;
+use crate::native::{Handle, Namespace, NativeFunction};
 use crate::tokens::Literal;
+use num_bigint::BigInt;
+use rust_decimal::Decimal;
+use std::cell::RefCell;
 use std::fmt;
+use std::rc::Rc;
+
+/// How many maps deep `Display` goes before eliding the rest, so printing a deeply nested
+/// map cannot overflow the stack.
+const MAX_PRINT_DEPTH: usize = 32;
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Value {
     String_(String),
     Bool(bool),
     Number(f64),
+    BigInt(BigInt),
+    Decimal(Decimal),
+    NativeFunction(NativeFunction),
+    Map(Rc<RefCell<Map>>),
+    Namespace(Rc<Namespace>),
+    Handle(Rc<Handle>),
     Nil,
 }
 
-impl fmt::Display for Value {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl Value {
+    pub fn map(map: Map) -> Self {
+        Self::Map(Rc::new(RefCell::new(map)))
+    }
+
+    /// Whether this is one of the numeric types, which arithmetic will not mix.
+    pub const fn is_numeric(&self) -> bool {
+        matches!(self, Self::Number(_) | Self::BigInt(_) | Self::Decimal(_))
+    }
+
+    /// The name `type()` reports for this value.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::String_(_) => "string",
+            Self::Bool(_) => "bool",
+            Self::Number(_) => "number",
+            Self::BigInt(_) => "bigint",
+            Self::Decimal(_) => "decimal",
+            Self::NativeFunction(_) => "native function",
+            Self::Map(_) => "map",
+            Self::Namespace(_) => "namespace",
+            Self::Handle(handle) => handle.kind,
+            Self::Nil => "nil",
+        }
+    }
+
+    /// Writes the value, quoting strings nested inside maps and eliding any map that
+    /// (directly or indirectly) contains itself or is nested too deeply.
+    fn write(
+        &self,
+        f: &mut fmt::Formatter,
+        seen: &mut Vec<*const RefCell<Map>>,
+        nested: bool,
+    ) -> fmt::Result {
         match self {
-            Self::String_(s) => write!(f, "{}", s),
+            Self::String_(s) if nested => write!(f, "{s:?}"),
+            Self::String_(s) => write!(f, "{s}"),
             Self::Nil => write!(f, "nil"),
+            Self::Number(n) if n.is_nan() => write!(f, "NaN"),
+            Self::Number(n) if n.is_infinite() => {
+                write!(f, "{}Infinity", if *n < 0.0 { "-" } else { "" })
+            }
             Self::Number(n) => {
                 let s = n.to_string();
                 write!(
@@ -26,17 +79,92 @@ impl fmt::Display for Value {
                     }
                 )
             }
-            Self::Bool(b) => write!(f, "{}", b),
+            Self::BigInt(n) => write!(f, "{n}"),
+            Self::Decimal(n) => write!(f, "{n}"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::NativeFunction(function) => match function.arity {
+                Some(arity) => write!(f, "<native fn {}({arity})>", function.name),
+                None => write!(f, "<native fn {}>", function.name),
+            },
+            Self::Namespace(namespace) => write!(f, "<namespace {}>", namespace.name),
+            Self::Handle(handle) => write!(f, "<{}>", handle.kind),
+            Self::Map(map) => {
+                let pointer = Rc::as_ptr(map);
+                if seen.len() >= MAX_PRINT_DEPTH || seen.contains(&pointer) {
+                    return write!(f, "{{...}}");
+                }
+                seen.push(pointer);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    key.write(f, seen, true)?;
+                    write!(f, ": ")?;
+                    value.write(f, seen, true)?;
+                }
+                seen.pop();
+                write!(f, "}}")
+            }
         }
     }
 }
 
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, &mut Vec::new(), false)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Self::Number(n)
+    }
+}
+
+impl From<BigInt> for Value {
+    fn from(n: BigInt) -> Self {
+        Self::BigInt(n)
+    }
+}
+
+impl From<Decimal> for Value {
+    fn from(n: Decimal) -> Self {
+        Self::Decimal(n)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::String_(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::String_(s.to_owned())
+    }
+}
+
+impl From<Map> for Value {
+    fn from(map: Map) -> Self {
+        Self::map(map)
+    }
+}
+
 impl From<Literal> for Value {
     fn from(l: Literal) -> Self {
         match l {
             Literal::String_(s) => Self::String_(s),
             Literal::Bool(b) => Self::Bool(b),
             Literal::Number(n) => Self::Number(n),
+            Literal::BigInt(n) => Self::BigInt(n),
             Literal::Nil => Self::Nil,
         }
     }
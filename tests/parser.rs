@@ -0,0 +1,80 @@
+//! The limit on call arguments, and where commas separate arguments rather than make a
+//! sequence. Lox has no function declarations yet, so there is no parameter limit to test
+//! alongside the argument one.
+
+use rox::ast::{ExprKind, StmtKind};
+use rox::diagnostic::Diagnostic;
+
+const TOO_MANY: &str = "Can't have more than 255 arguments.";
+
+fn call_with(arguments: usize) -> String {
+    format!("f({});", vec!["1"; arguments].join(", "))
+}
+
+fn errors(source: &str) -> Vec<Diagnostic> {
+    rox::parse(source).expect_err("the source should not compile")
+}
+
+#[test]
+fn accepts_255_arguments() {
+    assert!(rox::parse(&call_with(255)).is_ok());
+}
+
+#[test]
+fn rejects_256_arguments() {
+    let errors = errors(&call_with(256));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, TOO_MANY);
+    assert_eq!(errors[0].location, " at '1'");
+}
+
+#[test]
+fn reports_too_many_arguments_once_per_call() {
+    let errors = errors(&call_with(300));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, TOO_MANY);
+}
+
+#[test]
+fn keeps_parsing_after_too_many_arguments() {
+    let source = format!("{}\nvar = 1;\n{}", call_with(256), call_with(256));
+    let messages: Vec<_> = errors(&source)
+        .into_iter()
+        .map(|error| (error.line.get(), error.message))
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            (1, TOO_MANY.to_owned()),
+            (2, "Expect variable name.".to_owned()),
+            (3, TOO_MANY.to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn parses_print_with_two_arguments_as_a_call() {
+    let statements = rox::parse("print(1, 2);").unwrap();
+    assert_eq!(statements.len(), 1);
+    let StmtKind::Expression(expr) = &statements[0].kind else {
+        panic!("expected a call to print, got {:?}", statements[0].kind);
+    };
+    let ExprKind::Call(_, _, arguments) = &expr.kind else {
+        panic!("expected a call to print, got {:?}", expr.kind);
+    };
+    assert_eq!(arguments.len(), 2);
+}
+
+#[test]
+fn does_not_parse_a_sequence_after_print() {
+    let errors = errors("print (1), (2);");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Expect ';' after value.");
+    assert_eq!(errors[0].location, " at ','");
+}
+
+#[test]
+fn parses_sequences_in_expression_statements_and_for_clauses() {
+    assert!(rox::parse("var a; var b; a = 1, b = 2;").is_ok());
+    assert!(rox::parse("for (var i = 0; i < 1, i < 2; i = i + 1, i = i + 1) {}").is_ok());
+}
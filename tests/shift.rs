@@ -0,0 +1,98 @@
+//! The `<<` and `>>` operators on numbers, bigints and decimals.
+
+use rox::interpreter::{Interpreter, Output};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// Collects what a script prints.
+#[derive(Clone, Default)]
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `source`, returning what it printed, or the runtime error's message.
+fn run(source: &str) -> Result<String, String> {
+    let statements = rox::parse(source).expect("the source should compile");
+    let captured = Captured::default();
+    let mut interpreter = Interpreter::with_output(Output::new(captured.clone()));
+    match interpreter.interpret(&statements) {
+        Ok(()) => Ok(String::from_utf8(captured.0.take()).unwrap()),
+        Err(error) => Err(error.to_string().lines().next().unwrap().to_owned()),
+    }
+}
+
+fn prints(expression: &str) -> String {
+    run(&format!("print {expression};")).unwrap()
+}
+
+fn fails(expression: &str) -> String {
+    run(&format!("print {expression};")).unwrap_err()
+}
+
+#[test]
+fn shifts_numbers() {
+    assert_eq!(prints("1 << 3"), "8\n");
+    assert_eq!(prints("-16 >> 2"), "-4\n");
+    assert_eq!(prints("-1 >> 1"), "-1\n");
+}
+
+#[test]
+fn shifts_numbers_up_to_2_pow_53() {
+    assert_eq!(prints("1 << 53"), "9007199254740992\n");
+    assert_eq!(prints("-1 << 53"), "-9007199254740992\n");
+    assert_eq!(prints("9007199254740992 >> 53"), "1\n");
+}
+
+#[test]
+fn rejects_numbers_past_2_pow_53() {
+    assert_eq!(fails("2 << 53"), "Shift result is out of range.");
+    assert_eq!(
+        fails("9007199254740994 >> 1"),
+        "Shift operands must be integers within +/-2^53."
+    );
+    assert_eq!(fails("1 << 54"), "Shift amount must be between 0 and 53.");
+}
+
+#[test]
+fn rejects_negative_and_fractional_amounts() {
+    assert_eq!(fails("1 << -1"), "Shift amount must be between 0 and 53.");
+    assert_eq!(
+        fails("1 << 1.5"),
+        "Shift operands must be integers within +/-2^53."
+    );
+    assert_eq!(
+        fails("1n << -1n"),
+        "Shift amount must be between 0n and 1048576n."
+    );
+}
+
+#[test]
+fn shifts_bigints_up_to_the_limit() {
+    assert_eq!(prints("1n << 70n"), "1180591620717411303424\n");
+    assert_eq!(prints("(1n << (1n << 20n)) >> (1n << 20n)"), "1\n");
+    assert_eq!(
+        fails("1n << ((1n << 20n) + 1n)"),
+        "Shift amount must be between 0n and 1048576n."
+    );
+}
+
+#[test]
+fn rejects_decimal_operands() {
+    assert_eq!(
+        fails("decimal(\"1\") << decimal(\"2\")"),
+        "Shift operands must be integers."
+    );
+    assert_eq!(
+        fails("decimal(\"8\") >> decimal(\"1\")"),
+        "Shift operands must be integers."
+    );
+}